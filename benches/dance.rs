@@ -1,9 +1,18 @@
-use criterion::{Criterion, criterion_group, criterion_main};
+use criterion::measurement::WallTime;
+use criterion::{
+    BatchSize, BenchmarkGroup, Criterion, criterion_group, criterion_main,
+};
 
-use algorithm_m::choose::{Choose, FirstWins, MRVChooser, NoPreference};
-use algorithm_m::links::{INode, INodes, ONode};
+use algorithm_m::arrays::{OArrays, OArraysC};
+use algorithm_m::bits::BitProblem;
+use algorithm_m::choose::{self, Choose, FirstWins, MRVChooser, NoPreference};
 use algorithm_m::items::Items;
-use algorithm_m::{Count, ODance, Problem};
+use algorithm_m::links::{INode, INodes, ONode, ONodeC};
+use algorithm_m::polyomino::Packing;
+use algorithm_m::spec::Spec;
+use algorithm_m::sudoku::Sudoku;
+use algorithm_m::{Count, Data, ODance, Problem};
+use algorithm_m::{langford, polyomino, queens};
 
 fn bench_dance(c: &mut Criterion) {
     let items = INode::make_nodes(7, 0);
@@ -16,27 +25,181 @@ fn bench_dance(c: &mut Criterion) {
         vec![3, 4, 6],
     ];
     let opts = ONode::make_nodes(7, 0, 6, 16, os);
-    let tiebreak: FirstWins<INodes> = FirstWins::new();
+    let tiebreak: FirstWins<INodes> = choose::first_wins();
     let mut chooser = MRVChooser::new(NoPreference(), tiebreak);
     let mut problem = Problem::new(items, opts);
 
     c.bench_function("dance", |b| {
         b.iter(|| {
-            solve(&mut problem, &mut chooser);
+            solve(&mut problem, &mut chooser, usize::MAX);
         })
     });
 }
 
+fn bench_layouts(c: &mut Criterion) {
+    let os: Vec<Vec<Count>> = vec![
+        vec![2, 4],
+        vec![0, 3, 6],
+        vec![1, 2, 5],
+        vec![0, 3, 5],
+        vec![1, 6],
+        vec![3, 4, 6],
+    ];
+    let cs: Vec<Vec<(Count, Data)>> = vec![
+        vec![(0, 0), (1, 0), (3, 0), (4, 1)],
+        vec![(0, 0), (2, 0), (3, 1), (4, 0)],
+        vec![(0, 0), (3, 2)],
+        vec![(1, 0), (3, 1)],
+        vec![(2, 0), (4, 2)],
+    ];
+    let mut group = c.benchmark_group("layout");
+
+    let opts = ONode::make_nodes(7, 0, 6, 16, os.clone());
+    let items = INode::make_nodes(7, 0);
+    bench_solve(&mut group, "xc/aos", items, opts, usize::MAX);
+    let opts = OArrays::make_nodes(7, 0, 6, 16, os);
+    let items = INode::make_nodes(7, 0);
+    bench_solve(&mut group, "xc/soa", items, opts, usize::MAX);
+
+    let opts = ONodeC::make_nodes(3, 2, 5, 14, cs.clone());
+    let items = INode::make_nodes(3, 2);
+    bench_solve(&mut group, "xcc/aos", items, opts, usize::MAX);
+    let opts = OArraysC::make_nodes(3, 2, 5, 14, cs);
+    let items = INode::make_nodes(3, 2);
+    bench_solve(&mut group, "xcc/soa", items, opts, usize::MAX);
+
+    group.finish();
+}
+
+const INDEX: &str = if cfg!(feature = "unsafe-fast-index") {
+    "unchecked"
+} else {
+    "checked"
+};
+
+fn bench_instances(c: &mut Criterion) {
+    bench_spec(c, "queens-12", &queens::spec(12));
+    bench_spec(c, "langford-8", &langford::Pairs::langford(8).spec());
+
+    let sudoku = Sudoku::parse(
+        3,
+        "8........ ..36..... .7..9.2.. .5...7... ....457.. ...1...3. \
+         ..1....68 ..85...1. .9....4..",
+    )
+    .unwrap();
+    bench_spec(c, "sudoku", &sudoku.spec().uncolored().unwrap());
+
+    let pieces: Vec<_> =
+        polyomino::pentominoes().into_iter().map(|p| p.1).collect();
+    let mut group = c.benchmark_group("pentomino-6x10");
+    group.sample_size(10);
+    let packing = Packing::planar(&pieces, &polyomino::rectangle(10, 6));
+    bench_layouts_of(&mut group, &packing.spec(), 100);
+    group.finish();
+
+    let spec = queens::multi(6, 2);
+    let mut group = c.benchmark_group("queens-6x2-mcc");
+    let id = format!("INodesM+ONodes/{INDEX}");
+    bench_solve(&mut group, &id, spec.items_m(), spec.onodes(), usize::MAX);
+    let (items, opts) = (spec.items_m(), spec.colored().onodes_c());
+    let id = format!("INodesM+ONodesC/{INDEX}");
+    bench_solve(&mut group, &id, items, opts, usize::MAX);
+    group.finish();
+}
+
+fn bench_spec(c: &mut Criterion, name: &str, spec: &Spec<Count>) {
+    let mut group = c.benchmark_group(name);
+    bench_layouts_of(&mut group, spec, usize::MAX);
+    group.finish();
+}
+
+// Solves up to limit solutions with each item and option layout, and
+// with BitProblem if there are few enough items.
+fn bench_layouts_of(
+    group: &mut BenchmarkGroup<WallTime>,
+    spec: &Spec<Count>,
+    limit: usize,
+) {
+    bench_options(group, "INodes", spec.items(), spec, limit);
+    bench_options(group, "INodesM", spec.items_m(), spec, limit);
+    bench_bits(group, spec, limit);
+}
+
+fn bench_options<I: Items + Clone>(
+    group: &mut BenchmarkGroup<WallTime>,
+    name: &str,
+    items: I,
+    spec: &Spec<Count>,
+    limit: usize,
+) {
+    let colored = spec.colored();
+    let id = format!("{name}+ONodes/{INDEX}");
+    bench_solve(group, &id, items.clone(), spec.onodes(), limit);
+    let id = format!("{name}+ONodesC/{INDEX}");
+    bench_solve(group, &id, items.clone(), colored.onodes_c(), limit);
+    let id = format!("{name}+OArrays/{INDEX}");
+    bench_solve(group, &id, items.clone(), spec.oarrays(), limit);
+    let id = format!("{name}+OArraysC/{INDEX}");
+    bench_solve(group, &id, items, colored.oarrays_c(), limit);
+}
+
+fn bench_bits(
+    group: &mut BenchmarkGroup<WallTime>,
+    spec: &Spec<Count>,
+    limit: usize,
+) {
+    let (np, ns) = (spec.primary(), spec.secondary());
+    if np + ns > 128 || spec.has_bounds() {
+        return;
+    }
+    let opts = || spec.options().iter().map(|o| o.iter().copied());
+    group.bench_function("BitProblem", |b| {
+        b.iter_batched(
+            || BitProblem::new(np, ns, opts()),
+            |mut problem| {
+                let mut i = 0;
+                while i < limit && problem.next_solution() {
+                    i += 1;
+                }
+                i
+            },
+            BatchSize::SmallInput,
+        )
+    });
+}
+
+fn bench_solve<I, O>(
+    group: &mut BenchmarkGroup<WallTime>,
+    name: &str,
+    items: I,
+    opts: O,
+    limit: usize,
+) where
+    I: Items + Clone,
+    O: ODance + Clone,
+{
+    let mut chooser =
+        MRVChooser::new(NoPreference(), choose::first_wins::<I>());
+    group.bench_function(name, |b| {
+        b.iter_batched(
+            || Problem::new(items.clone(), opts.clone()),
+            |mut problem| solve(&mut problem, &mut chooser, limit),
+            BatchSize::SmallInput,
+        )
+    });
+}
+
 fn solve<I: Items, O: ODance, C: Choose<I>>(
     problem: &mut Problem<I, O>,
     chooser: &mut C,
+    limit: usize,
 ) -> usize {
     let mut i = 0;
-    while problem.next_solution(chooser) {
+    while i < limit && problem.next_solution(chooser) {
         i += 1;
     }
     i
 }
 
-criterion_group!(benches, bench_dance);
+criterion_group!(benches, bench_dance, bench_layouts, bench_instances);
 criterion_main!(benches);
@@ -0,0 +1,237 @@
+#![allow(clippy::unnecessary_cast)]
+use crate::index::{Index, node_count};
+use crate::{Count, Data, Link, ODance};
+
+#[inline(always)]
+fn get<T>(v: &mut [T], i: Link) -> &mut T {
+    if cfg!(feature = "unsafe-fast-index") {
+        unsafe { v.get_unchecked_mut(i as usize) }
+    } else {
+        &mut v[i as usize]
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct OArrays<L: Index = Link> {
+    hdr_info: Vec<L::Signed>,
+    up: Vec<L>,
+    down: Vec<L>,
+    size: Count,
+}
+
+impl OArrays {
+    pub fn make_nodes(
+        np: Count,
+        ns: Count,
+        m: Count,
+        l: Count,
+        opt_spec: impl IntoIterator<Item = impl IntoIterator<Item = Count>>,
+    ) -> OArrays {
+        OArrays::new(np, ns, m, l, opt_spec)
+    }
+}
+
+impl<L: Index> OArrays<L> {
+    pub fn new(
+        np: Count,
+        ns: Count,
+        m: Count,
+        l: Count,
+        opt_spec: impl IntoIterator<Item = impl IntoIterator<Item = Count>>,
+    ) -> OArrays<L> {
+        let n = np.checked_add(ns).expect("too many items");
+        let len = node_count::<L>(n, m, l);
+        let mut nodes = OArrays {
+            hdr_info: vec![Default::default(); len],
+            up: vec![Default::default(); len],
+            down: vec![Default::default(); len],
+            size: m,
+        };
+        nodes.init_links(np, ns, opt_spec);
+        nodes
+    }
+}
+
+impl<L: Index> ODance for OArrays<L> {
+    type Spec = Count;
+
+    #[inline(always)]
+    fn olen(&mut self, i: Link) -> Data {
+        L::data(*get(&mut self.hdr_info, i))
+    }
+
+    #[inline(always)]
+    fn set_olen(&mut self, i: Link, len: Data) {
+        *get(&mut self.hdr_info, i) = L::from_data(len);
+    }
+
+    #[inline(always)]
+    fn top(&mut self, i: Link) -> Data {
+        L::data(*get(&mut self.hdr_info, i))
+    }
+
+    #[inline(always)]
+    fn set_top(&mut self, i: Link, t: Data) {
+        *get(&mut self.hdr_info, i) = L::from_data(t);
+    }
+
+    #[inline(always)]
+    fn ulink(&mut self, i: Link) -> Link {
+        get(&mut self.up, i).link()
+    }
+
+    #[inline(always)]
+    fn set_ulink(&mut self, i: Link, x: Link) {
+        *get(&mut self.up, i) = L::from_link(x);
+    }
+
+    #[inline(always)]
+    fn dlink(&mut self, i: Link) -> Link {
+        get(&mut self.down, i).link()
+    }
+
+    #[inline(always)]
+    fn set_dlink(&mut self, i: Link, x: Link) {
+        *get(&mut self.down, i) = L::from_link(x);
+    }
+
+    #[inline(always)]
+    fn get_color(&mut self, _i: Link) -> Data {
+        0
+    }
+
+    #[inline(always)]
+    fn set_color(&mut self, _i: Link, _c: Data) {}
+
+    fn size(&self) -> Count {
+        self.size
+    }
+
+    fn nodes(&self) -> Count {
+        self.up.len() as Count
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct OArraysC<L: Index = Link> {
+    hdr_info: Vec<L::Signed>,
+    up: Vec<L>,
+    down: Vec<L>,
+    color: Vec<L::Signed>,
+    items: Count,
+    size: Count,
+}
+
+impl OArraysC {
+    pub fn make_nodes(
+        np: Count,
+        ns: Count,
+        m: Count,
+        l: Count,
+        opt_spec: impl IntoIterator<Item = impl IntoIterator<Item = (Count, Data)>>,
+    ) -> OArraysC {
+        OArraysC::new(np, ns, m, l, opt_spec)
+    }
+}
+
+impl<L: Index> OArraysC<L> {
+    pub fn new(
+        np: Count,
+        ns: Count,
+        m: Count,
+        l: Count,
+        opt_spec: impl IntoIterator<Item = impl IntoIterator<Item = (Count, Data)>>,
+    ) -> OArraysC<L> {
+        let n = np.checked_add(ns).expect("too many items");
+        let len = node_count::<L>(n, m, l);
+        let mut nodes = OArraysC {
+            hdr_info: vec![Default::default(); len],
+            up: vec![Default::default(); len],
+            down: vec![Default::default(); len],
+            color: vec![Default::default(); len],
+            items: n,
+            size: m,
+        };
+        let opt_spec = opt_spec.into_iter().map(|opt| {
+            opt.into_iter()
+                .inspect(|&(_, c)| assert!(L::fits(c), "color out of range"))
+        });
+        nodes.init_links(np, ns, opt_spec);
+        nodes
+    }
+}
+
+impl<L: Index> PartialEq for OArraysC<L> {
+    // Colors stored in item headers are scratch space for purify.
+    fn eq(&self, other: &Self) -> bool {
+        let n = (self.items + 1) as usize;
+        self.items == other.items
+            && self.size == other.size
+            && self.hdr_info == other.hdr_info
+            && self.up == other.up
+            && self.down == other.down
+            && self.color.get(n..) == other.color.get(n..)
+    }
+}
+
+impl<L: Index> ODance for OArraysC<L> {
+    type Spec = (Count, Data);
+
+    #[inline(always)]
+    fn olen(&mut self, i: Link) -> Data {
+        L::data(*get(&mut self.hdr_info, i))
+    }
+
+    #[inline(always)]
+    fn set_olen(&mut self, i: Link, len: Data) {
+        *get(&mut self.hdr_info, i) = L::from_data(len);
+    }
+
+    #[inline(always)]
+    fn top(&mut self, i: Link) -> Data {
+        L::data(*get(&mut self.hdr_info, i))
+    }
+
+    #[inline(always)]
+    fn set_top(&mut self, i: Link, t: Data) {
+        *get(&mut self.hdr_info, i) = L::from_data(t);
+    }
+
+    #[inline(always)]
+    fn ulink(&mut self, i: Link) -> Link {
+        get(&mut self.up, i).link()
+    }
+
+    #[inline(always)]
+    fn set_ulink(&mut self, i: Link, x: Link) {
+        *get(&mut self.up, i) = L::from_link(x);
+    }
+
+    #[inline(always)]
+    fn dlink(&mut self, i: Link) -> Link {
+        get(&mut self.down, i).link()
+    }
+
+    #[inline(always)]
+    fn set_dlink(&mut self, i: Link, x: Link) {
+        *get(&mut self.down, i) = L::from_link(x);
+    }
+
+    #[inline(always)]
+    fn get_color(&mut self, i: Link) -> Data {
+        L::data(*get(&mut self.color, i))
+    }
+
+    #[inline(always)]
+    fn set_color(&mut self, i: Link, c: Data) {
+        *get(&mut self.color, i) = L::from_data(c);
+    }
+
+    fn size(&self) -> Count {
+        self.size
+    }
+
+    fn nodes(&self) -> Count {
+        self.up.len() as Count
+    }
+}
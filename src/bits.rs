@@ -0,0 +1,292 @@
+#![allow(clippy::unnecessary_cast)]
+use crate::{Count, OptSpec};
+
+type Mask = u128;
+
+const MAX_ITEMS: Count = Mask::BITS as Count;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct Level {
+    item: Count,
+    k: usize,
+    opt: Option<usize>,
+}
+
+// Options are bit positions. Two options clash if they share an uncolored
+// item or give an item different colors, which does not depend on what
+// else is chosen, so each option keeps the set of options it clashes with
+// (itself among them) and choosing it clears that set from the options
+// still live. Each level keeps its own live set, so undoing costs nothing,
+// and the item to branch on is the one with the smallest popcount. The
+// clash sets take m * m / 8 bytes for m options.
+#[derive(Clone, Debug)]
+pub struct BitProblem {
+    primary: Count,
+    secondary: Count,
+    // Words per set of options.
+    words: usize,
+    // The primary items of each option.
+    masks: Vec<Mask>,
+    clashes: Vec<u64>,
+    by_item: Vec<Vec<usize>>,
+    // The options of each primary item, as (word, bits) for the words
+    // that have any.
+    item_words: Vec<Vec<(usize, u64)>>,
+    // The live options at each level, and below the first.
+    live: Vec<u64>,
+    all: Mask,
+    covered: Mask,
+    levels: Vec<Level>,
+    o: Vec<isize>,
+    restart: bool,
+}
+
+impl BitProblem {
+    pub fn new(
+        np: Count,
+        ns: Count,
+        opt_spec: impl IntoIterator<Item = impl IntoIterator<Item = impl OptSpec>>,
+    ) -> BitProblem {
+        let n = np + ns;
+        assert!(n <= MAX_ITEMS, "too many items for a bit mask");
+        let mut masks = Vec::new();
+        let mut by_item = vec![Vec::new(); np as usize];
+        // The options of each item, with the colors they give it.
+        let mut holders = vec![Vec::new(); n as usize];
+        for (k, spec) in opt_spec.into_iter().enumerate() {
+            let mut mask = 0;
+            for s in spec.into_iter() {
+                let j = s.get_item();
+                assert!(j < n, "item out of range");
+                let c = if j >= np { s.get_color() } else { 0 };
+                if j < np {
+                    mask |= 1 << j;
+                    by_item[j as usize].push(k);
+                }
+                holders[j as usize].push((k, c));
+            }
+            masks.push(mask);
+        }
+        let m = masks.len();
+        let words = m.div_ceil(64);
+        let mut clashes = vec![0; m * words];
+        for opts in &holders {
+            for &(k, c) in opts {
+                for &(q, d) in opts {
+                    if c == 0 || c != d {
+                        clashes[k * words + q / 64] |= 1 << (q % 64);
+                    }
+                }
+            }
+        }
+        let mut live = vec![u64::MAX; words];
+        if m % 64 != 0 {
+            live[m / 64] = (1 << (m % 64)) - 1;
+        }
+        let item_words = by_item
+            .iter()
+            .map(|opts| {
+                let mut set: Vec<(usize, u64)> = Vec::new();
+                for &k in opts {
+                    match set.last_mut() {
+                        Some((w, bits)) if *w == k / 64 => {
+                            *bits |= 1 << (k % 64)
+                        }
+                        _ => set.push((k / 64, 1 << (k % 64))),
+                    }
+                }
+                set
+            })
+            .collect();
+        BitProblem {
+            primary: np,
+            secondary: ns,
+            words,
+            masks,
+            clashes,
+            by_item,
+            item_words,
+            live,
+            all: if np == 0 {
+                0
+            } else {
+                Mask::MAX >> (MAX_ITEMS - np)
+            },
+            covered: 0,
+            levels: Vec::new(),
+            o: Vec::new(),
+            restart: false,
+        }
+    }
+
+    pub fn primary(&self) -> Count {
+        self.primary
+    }
+
+    pub fn secondary(&self) -> Count {
+        self.secondary
+    }
+
+    pub fn next_solution(&mut self) -> bool {
+        if self.restart {
+            self.restart = false;
+            if !self.advance() {
+                return false;
+            }
+        }
+        loop {
+            if self.covered == self.all {
+                self.restart = true;
+                return true;
+            }
+            if let Some(item) = self.choose() {
+                self.levels.push(Level { item, k: 0, opt: None });
+            }
+            if !self.advance() {
+                return false;
+            }
+        }
+    }
+
+    pub fn find_options(&mut self) {
+        self.o.clear();
+        for level in &self.levels {
+            if let Some(k) = level.opt {
+                self.o.push(k as isize);
+            }
+        }
+    }
+
+    pub fn get_options(&self) -> &[isize] {
+        &self.o
+    }
+
+    // Returns the uncovered primary item with the fewest live options, or
+    // None if some item has none left.
+    fn choose(&self) -> Option<Count> {
+        let live = &self.live[self.live.len() - self.words..];
+        let mut min = u32::MAX;
+        let mut best = None;
+        let mut left = self.all & !self.covered;
+        while left != 0 {
+            let i = left.trailing_zeros();
+            left &= left - 1;
+            let words = self.item_words[i as usize].iter();
+            let len = words.map(|&(w, bits)| (live[w] & bits).count_ones());
+            let len: u32 = len.sum();
+            if len == 0 {
+                return None;
+            }
+            if len < min {
+                min = len;
+                best = Some(i as Count);
+            }
+        }
+        best
+    }
+
+    // Moves the deepest level on to its next live option, popping levels
+    // that have run out. Returns false once every level is exhausted.
+    fn advance(&mut self) -> bool {
+        let words = self.words;
+        while let Some(l) = self.levels.len().checked_sub(1) {
+            if let Some(k) = self.levels[l].opt.take() {
+                self.covered &= !self.masks[k];
+                self.live.truncate(self.live.len() - words);
+            }
+            let item = self.levels[l].item as usize;
+            let base = self.live.len() - words;
+            let opts = &self.by_item[item][self.levels[l].k..];
+            let live = &self.live[base..];
+            if let Some(e) =
+                opts.iter().position(|&k| live[k / 64] & 1 << (k % 64) != 0)
+            {
+                let k = opts[e];
+                self.levels[l].k += e + 1;
+                self.levels[l].opt = Some(k);
+                self.covered |= self.masks[k];
+                let clashes = &self.clashes[k * words..(k + 1) * words];
+                for (w, &clash) in clashes.iter().enumerate() {
+                    self.live.push(self.live[base + w] & !clash);
+                }
+                return true;
+            }
+            self.levels.pop();
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::random::Rng;
+    use crate::reference;
+    use crate::spec::taocp_xcc;
+
+    fn verify_solutions(mut problem: BitProblem, expected: Vec<Vec<isize>>) {
+        let mut solutions: Vec<Vec<isize>> = Vec::new();
+        while problem.next_solution() {
+            assert!(solutions.len() <= expected.len(), "too many solutions");
+            problem.find_options();
+            let mut o = problem.get_options().to_vec();
+            o.sort();
+            solutions.push(o);
+        }
+        solutions.sort();
+        let mut expected = expected;
+        expected.sort();
+        assert_eq!(solutions, expected, "wrong solutions");
+        assert!(
+            problem.levels.is_empty()
+                && problem.covered == 0
+                && problem.live.len() == problem.words,
+            "initial state not restored"
+        );
+    }
+
+    #[test]
+    // TAocp Vol. 4B p. 66
+    fn test_xc() {
+        let opt_spec: Vec<Vec<Count>> = vec![
+            vec![2, 4],
+            vec![0, 3, 6],
+            vec![1, 2, 5],
+            vec![0, 3, 5],
+            vec![1, 6],
+            vec![3, 4, 6],
+        ];
+        verify_solutions(BitProblem::new(7, 0, opt_spec), vec![vec![0, 3, 4]]);
+    }
+
+    #[test]
+    fn test_xcc() {
+        let opt_spec = taocp_xcc().options().to_vec();
+        verify_solutions(BitProblem::new(3, 2, opt_spec), vec![vec![1, 3]]);
+    }
+
+    #[test]
+    fn test_wide() {
+        // Dominoes covering a 1x128 strip: exactly one tiling.
+        let opt_spec: Vec<Vec<Count>> =
+            (0..127).map(|i| vec![i, i + 1]).collect();
+        let expected = vec![(0..64).map(|i| 2 * i).collect()];
+        verify_solutions(BitProblem::new(128, 0, opt_spec), expected);
+    }
+
+    #[test]
+    fn test_reference() {
+        let mut rng = Rng::new(3);
+        for _ in 0..300 {
+            let spec = reference::random_spec(&mut rng, true, false);
+            let opt_spec = spec.options().iter().map(|o| o.iter().copied());
+            let problem =
+                BitProblem::new(spec.primary(), spec.secondary(), opt_spec);
+            let expected = reference::solutions(&spec).into_iter();
+            let expected = expected
+                .map(|s| s.into_iter().map(|k| k as isize).collect())
+                .collect();
+            verify_solutions(problem, expected);
+        }
+    }
+}
@@ -0,0 +1,419 @@
+#![allow(clippy::unnecessary_cast)]
+use crate::choose::{self, FirstWins, MRVChooser, NoPreference};
+use crate::links::{INodes, ONodesC};
+use crate::spec::Spec;
+use crate::sudoku::digit_sets;
+use crate::{Count, Data, Problem};
+
+// The (cell, digit) pairs placed by an option.
+type Filling = Vec<(usize, usize)>;
+
+// How the digits of a KenKen cage combine into its target. Sub and Div
+// cages have two cells; an Eq cage is a single given cell.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Eq,
+}
+
+impl Op {
+    // Cages have the right number of cells for their operation.
+    fn holds(self, digits: &[usize], target: usize) -> bool {
+        match self {
+            Op::Add => digits.iter().sum::<usize>() == target,
+            Op::Mul => digits.iter().product::<usize>() == target,
+            Op::Sub => digits[0].abs_diff(digits[1]) == target,
+            Op::Div => {
+                let (a, b) = (digits[0], digits[1]);
+                a.max(b) == a.min(b) * target
+            }
+            Op::Eq => digits[0] == target,
+        }
+    }
+
+    // Whether a cage whose first digits are given might still reach the
+    // target.
+    fn may_hold(self, digits: &[usize], target: usize) -> bool {
+        match self {
+            Op::Add => digits.iter().sum::<usize>() <= target,
+            Op::Mul => target.is_multiple_of(digits.iter().product()),
+            _ => true,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Cage {
+    pub cells: Vec<usize>,
+    pub op: Op,
+    pub target: usize,
+}
+
+// An n x n Latin square of the digits 1..=n, divided into cages whose
+// digits must reach their targets. Digits may repeat within a cage, but
+// not within a row or column.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct KenKen {
+    n: usize,
+    cages: Vec<Cage>,
+}
+
+impl KenKen {
+    pub fn new(n: usize) -> KenKen {
+        assert!(n > 0);
+        KenKen { n, cages: Vec::new() }
+    }
+
+    pub fn add_cage(&mut self, cells: &[usize], op: Op, target: usize) {
+        let len = match op {
+            Op::Sub | Op::Div => 2,
+            Op::Eq => 1,
+            _ => cells.len(),
+        };
+        assert!(!cells.is_empty() && cells.len() == len, "wrong cage size");
+        assert!(cells.iter().all(|&c| c < self.n * self.n), "no such cell");
+        self.cages.push(Cage { cells: cells.to_vec(), op, target });
+    }
+
+    pub fn cages(&self) -> &[Cage] {
+        &self.cages
+    }
+
+    pub fn spec(&self) -> Spec<(Count, Data)> {
+        self.build().0
+    }
+
+    // Filled grids, row by row.
+    pub fn solutions(&self) -> Solutions {
+        let (spec, fillings) = self.build();
+        Solutions::new(spec, fillings, self.n * self.n)
+    }
+
+    // Items are the cages, then (row, digit) and (column, digit) pairs, all
+    // primary, and the cells, secondary and colored by their digit. There
+    // is an option for every filling of every cage, and one for every cell
+    // of every row or column and every digit, so a line gets each digit
+    // from the cell whose cage put it there.
+    fn build(&self) -> (Spec<(Count, Data)>, Vec<Filling>) {
+        let n = self.n;
+        let mut covered = vec![false; n * n];
+        for cell in self.cages.iter().flat_map(|cage| &cage.cells) {
+            assert!(!covered[*cell], "cages overlap");
+            covered[*cell] = true;
+        }
+        assert!(covered.iter().all(|&c| c), "cell not in any cage");
+
+        let k = self.cages.len();
+        let row = |cell: usize, d: usize| k + (cell / n) * n + d - 1;
+        let col = |cell: usize, d: usize| k + n * n + (cell % n) * n + d - 1;
+        let at = |cell: usize| (k + 2 * n * n + cell) as Count;
+        let mut spec = Spec::new((k + 2 * n * n) as Count, (n * n) as Count);
+        let mut fillings = Vec::new();
+        for (g, cage) in self.cages.iter().enumerate() {
+            for digits in self.fillings(cage) {
+                let mut opt = vec![(g as Count, 0)];
+                for (&cell, &d) in cage.cells.iter().zip(&digits) {
+                    opt.push((at(cell), d as Data));
+                }
+                spec.add_option(opt);
+                fillings.push(cage.cells.iter().copied().zip(digits).collect());
+            }
+        }
+        for cell in 0..n * n {
+            for d in 1..=n {
+                for line in [row(cell, d), col(cell, d)] {
+                    spec.add_option([
+                        (line as Count, 0),
+                        (at(cell), d as Data),
+                    ]);
+                    fillings.push(Vec::new());
+                }
+            }
+        }
+        (spec, fillings)
+    }
+
+    // The digits that can go in the cells of a cage without repeating a
+    // digit in a row or column.
+    fn fillings(&self, cage: &Cage) -> Vec<Vec<usize>> {
+        let n = self.n;
+        let clash = |a: usize, b: usize| a / n == b / n || a % n == b % n;
+        let mut fillings = Vec::new();
+        let mut stack = vec![Vec::new()];
+        while let Some(digits) = stack.pop() {
+            let i = digits.len();
+            if i == cage.cells.len() {
+                if cage.op.holds(&digits, cage.target) {
+                    fillings.push(digits);
+                }
+                continue;
+            }
+            for d in 1..=n {
+                let repeats = (0..i).any(|j| {
+                    digits[j] == d && clash(cage.cells[i], cage.cells[j])
+                });
+                if repeats {
+                    continue;
+                }
+                let mut next = digits.clone();
+                next.push(d);
+                if cage.op.may_hold(&next, cage.target) {
+                    stack.push(next);
+                }
+            }
+        }
+        fillings
+    }
+}
+
+// A Kakuro grid: every run of white cells holds distinct digits 1..=9
+// adding up to its sum. Cells not in any run are black.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Kakuro {
+    cells: usize,
+    runs: Vec<(Vec<usize>, usize)>,
+}
+
+impl Kakuro {
+    pub fn new(width: usize, height: usize) -> Kakuro {
+        Kakuro { cells: width * height, runs: Vec::new() }
+    }
+
+    pub fn add_run(&mut self, cells: &[usize], sum: usize) {
+        assert!(!cells.is_empty() && cells.len() <= 9, "wrong run length");
+        assert!(cells.iter().all(|&c| c < self.cells), "no such cell");
+        self.runs.push((cells.to_vec(), sum));
+    }
+
+    pub fn spec(&self) -> Spec<(Count, Data)> {
+        self.build().0
+    }
+
+    // Filled grids, row by row, with 0 for black cells.
+    pub fn solutions(&self) -> Solutions {
+        let (spec, fillings) = self.build();
+        Solutions::new(spec, fillings, self.cells)
+    }
+
+    // Runs are primary items and cells secondary items, colored by their
+    // digit. There is an option for every ordering of every set of digits
+    // with the right sum.
+    fn build(&self) -> (Spec<(Count, Data)>, Vec<Filling>) {
+        let np = self.runs.len();
+        let mut spec = Spec::new(np as Count, self.cells as Count);
+        let mut fillings = Vec::new();
+        for (r, (cells, sum)) in self.runs.iter().enumerate() {
+            for set in digit_sets(9, cells.len(), *sum) {
+                let digits: Vec<usize> =
+                    (1..=9).filter(|d| set & (1 << (d - 1)) != 0).collect();
+                for perm in permutations(&digits) {
+                    let mut opt = vec![(r as Count, 0)];
+                    for (&cell, &d) in cells.iter().zip(&perm) {
+                        opt.push(((np + cell) as Count, d as Data));
+                    }
+                    spec.add_option(opt);
+                    fillings.push(cells.iter().copied().zip(perm).collect());
+                }
+            }
+        }
+        (spec, fillings)
+    }
+}
+
+fn permutations(digits: &[usize]) -> Vec<Vec<usize>> {
+    if digits.is_empty() {
+        return vec![Vec::new()];
+    }
+    let mut perms = Vec::new();
+    for (i, &d) in digits.iter().enumerate() {
+        let rest = [&digits[..i], &digits[i + 1..]].concat();
+        for mut perm in permutations(&rest) {
+            perm.insert(0, d);
+            perms.push(perm);
+        }
+    }
+    perms
+}
+
+pub struct Solutions {
+    problem: Problem<INodes, ONodesC>,
+    chooser: MRVChooser<NoPreference, FirstWins<INodes>>,
+    fillings: Vec<Filling>,
+    cells: usize,
+    done: bool,
+}
+
+impl Solutions {
+    fn new(
+        spec: Spec<(Count, Data)>,
+        fillings: Vec<Filling>,
+        cells: usize,
+    ) -> Solutions {
+        Solutions {
+            problem: Problem::new(spec.items(), spec.onodes_c()),
+            chooser: MRVChooser::new(
+                choose::no_preference(),
+                choose::first_wins(),
+            ),
+            fillings,
+            cells,
+            done: false,
+        }
+    }
+}
+
+impl Iterator for Solutions {
+    type Item = Vec<usize>;
+
+    fn next(&mut self) -> Option<Vec<usize>> {
+        if self.done || !self.problem.next_solution(&mut self.chooser) {
+            self.done = true;
+            return None;
+        }
+        self.problem.find_options();
+        let mut grid = vec![0; self.cells];
+        for &k in self.problem.get_options() {
+            for &(cell, d) in &self.fillings[k as usize] {
+                grid[cell] = d;
+            }
+        }
+        Some(grid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn latin_squares(n: usize) -> Vec<Vec<usize>> {
+        let rows = permutations(&(1..=n).collect::<Vec<_>>());
+        let mut squares = vec![Vec::new()];
+        for _ in 0..n {
+            let mut next = Vec::new();
+            for square in &squares {
+                for row in &rows {
+                    let fits = (0..n).all(|c| {
+                        square.chunks(n).all(|prev: &[usize]| prev[c] != row[c])
+                    });
+                    if fits {
+                        next.push([&square[..], row].concat());
+                    }
+                }
+            }
+            squares = next;
+        }
+        squares
+    }
+
+    #[test]
+    fn test_kenken() {
+        use Op::*;
+        let mut kenken = KenKen::new(4);
+        for (cells, op, target) in [
+            (&[0, 4][..], Sub, 2),
+            (&[1, 2], Mul, 6),
+            (&[3, 7], Div, 2),
+            (&[5, 6, 10], Add, 9),
+            (&[8, 12], Add, 6),
+            (&[9, 13], Mul, 3),
+            (&[11, 15], Sub, 2),
+            (&[14], Eq, 2),
+        ] {
+            kenken.add_cage(cells, op, target);
+        }
+        let mut expected: Vec<Vec<usize>> = latin_squares(4)
+            .into_iter()
+            .filter(|grid| {
+                kenken.cages().iter().all(|cage| {
+                    let digits: Vec<usize> =
+                        cage.cells.iter().map(|&c| grid[c]).collect();
+                    cage.op.holds(&digits, cage.target)
+                })
+            })
+            .collect();
+        let mut found: Vec<Vec<usize>> = kenken.solutions().collect();
+        found.sort();
+        expected.sort();
+        assert_eq!(found, expected);
+        assert!(
+            found.contains(&vec![
+                1, 2, 3, 4, 3, 4, 1, 2, 2, 1, 4, 3, 4, 3, 2, 1
+            ])
+        );
+
+        // With every row a cage, any Latin square will do.
+        let mut rows = KenKen::new(4);
+        for r in 0..4 {
+            rows.add_cage(&[4 * r, 4 * r + 1, 4 * r + 2, 4 * r + 3], Add, 10);
+        }
+        assert_eq!(rows.solutions().count(), 576);
+    }
+
+    #[test]
+    fn test_kenken_lines() {
+        // Cells 0 and 1 share a row, so the two cages cannot both put a 1
+        // there; cell 0's item gets its color from the cage and the line.
+        let mut kenken = KenKen::new(2);
+        kenken.add_cage(&[0], Op::Eq, 1);
+        kenken.add_cage(&[1], Op::Eq, 1);
+        kenken.add_cage(&[2, 3], Op::Add, 3);
+        assert_eq!(kenken.solutions().count(), 0);
+        let spec = kenken.spec();
+        assert_eq!((spec.primary(), spec.secondary()), (3 + 8, 4));
+        assert_eq!(spec.options()[0], [(0, 0), (11, 1)]);
+        assert!(spec.options().contains(&vec![(3, 0), (11, 1)]));
+
+        let mut kenken = KenKen::new(2);
+        kenken.add_cage(&[0], Op::Eq, 1);
+        kenken.add_cage(&[1], Op::Eq, 2);
+        kenken.add_cage(&[2, 3], Op::Add, 3);
+        let found: Vec<Vec<usize>> = kenken.solutions().collect();
+        assert_eq!(found, [[1, 2, 2, 1]]);
+    }
+
+    #[test]
+    fn test_kakuro() {
+        let mut kakuro = Kakuro::new(2, 2);
+        kakuro.add_run(&[0, 1], 3);
+        kakuro.add_run(&[2, 3], 4);
+        kakuro.add_run(&[0, 2], 4);
+        kakuro.add_run(&[1, 3], 3);
+        let found: Vec<Vec<usize>> = kakuro.solutions().collect();
+        assert_eq!(found, [[1, 2, 3, 1]]);
+
+        let runs = [
+            (vec![0, 1, 2], 6),
+            (vec![3, 4, 5], 15),
+            (vec![0, 3], 3),
+            (vec![1, 4], 10),
+            (vec![2, 5], 8),
+        ];
+        let mut kakuro = Kakuro::new(3, 2);
+        for (cells, sum) in &runs {
+            kakuro.add_run(cells, *sum);
+        }
+        let mut expected = Vec::new();
+        for code in 0..9usize.pow(6) {
+            let grid: Vec<usize> =
+                (0..6).map(|i| code / 9usize.pow(i) % 9 + 1).collect();
+            let fits = runs.iter().all(|(cells, sum)| {
+                let digits: Vec<usize> =
+                    cells.iter().map(|&c| grid[c]).collect();
+                let distinct = (1..=9)
+                    .all(|d| digits.iter().filter(|&&x| x == d).count() <= 1);
+                distinct && digits.iter().sum::<usize>() == *sum
+            });
+            if fits {
+                expected.push(grid);
+            }
+        }
+        let mut found: Vec<Vec<usize>> = kakuro.solutions().collect();
+        found.sort();
+        expected.sort();
+        assert!(!expected.is_empty());
+        assert_eq!(found, expected);
+    }
+}
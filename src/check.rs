@@ -0,0 +1,336 @@
+#![allow(clippy::unnecessary_cast)]
+use crate::items::Items;
+use crate::{Count, Data, Link, ODance};
+
+// Checks that the items and options are linked as they should be between
+// the steps of Algorithm M, returning the first thing found wrong:
+// - the lists of active primary and secondary items are doubly linked;
+// - each item's list of options is doubly linked, through nodes of that
+//   item, and OLEN is its length;
+// - spacers have TOP 0, -1, -2, ..., each linking to the first and last
+//   nodes of the options next to it;
+// - nodes of primary items have color 0, and every other node has the
+//   color given, one per node, by colors (see below), except that it is -1
+//   while its item is purified with that color.
+// Nodes out of the lists (hidden options) are only checked for their
+// place between spacers and their colors.
+pub fn check<I: Items, O: ODance>(
+    items: &mut I,
+    opts: &mut O,
+    colors: &[Data],
+) -> Result<(), String> {
+    let n1 = items.primary();
+    let n = n1 + items.secondary();
+    check_items(items, 0, 1, n1, n)?;
+    check_items(items, n + 1, n1 + 1, n, n)?;
+    check_spacers(opts, n1, n, colors)?;
+    for i in 1..=n {
+        check_options(opts, i, n)?;
+    }
+    Ok(())
+}
+
+// The colors init_links gave the nodes, as far as they can be told from the
+// nodes as they are: purify only ever changes a color to -1, and leaves the
+// color it took away in the item's header until unpurify puts it back.
+// Headers get none.
+pub fn colors<I: Items, O: ODance>(items: &mut I, opts: &mut O) -> Vec<Data> {
+    let n = items.primary() + items.secondary();
+    (0..opts.nodes())
+        .map(|r| {
+            let c = opts.get_color(r);
+            let t = opts.top(r);
+            match c {
+                _ if r <= n => 0,
+                -1 if t > 0 => opts.get_color(t as Link),
+                _ => c,
+            }
+        })
+        .collect()
+}
+
+// The list with this head, of items from lo to hi.
+fn check_items<I: Items>(
+    items: &mut I,
+    head: Link,
+    lo: Link,
+    hi: Link,
+    n: Count,
+) -> Result<(), String> {
+    let mut seen = vec![false; n as usize + 2];
+    let mut x = head;
+    loop {
+        let r = items.rlink(x);
+        if r > n + 1 {
+            return Err(format!("RLINK({x}) = {r} is out of range"));
+        }
+        if items.llink(r) != x {
+            let l = items.llink(r);
+            return Err(format!("RLINK({x}) = {r} but LLINK({r}) = {l}"));
+        }
+        if r == head {
+            return Ok(());
+        }
+        if r < lo || hi < r {
+            return Err(format!("item {r} is in the list headed by {head}"));
+        }
+        if seen[r as usize] {
+            return Err(format!("item {r} is listed twice"));
+        }
+        seen[r as usize] = true;
+        x = r;
+    }
+}
+
+fn check_spacers<O: ODance>(
+    opts: &mut O,
+    n1: Count,
+    n: Count,
+    colors: &[Data],
+) -> Result<(), String> {
+    let len = opts.nodes();
+    if colors.len() != len as usize {
+        return Err(format!("{} colors for {len} nodes", colors.len()));
+    }
+    let mut p = n + 1;
+    for m in 0..=opts.size() {
+        if p >= len {
+            return Err(format!("spacer {m} is missing"));
+        }
+        if opts.top(p) != -(m as Data) {
+            let t = opts.top(p);
+            return Err(format!("TOP({p}) = {t} for spacer {m}"));
+        }
+        if m == opts.size() {
+            break;
+        }
+        let q = opts.dlink(p);
+        if q < p || q + 1 >= len {
+            return Err(format!("DLINK({p}) = {q} for spacer {m}"));
+        }
+        for r in p + 1..=q {
+            let t = opts.top(r);
+            if t <= 0 || t as Count > n {
+                return Err(format!("TOP({r}) = {t} in option {m}"));
+            }
+            let (c, o) = (opts.get_color(r), colors[r as usize]);
+            if t as Count <= n1 && c != 0 {
+                return Err(format!("node {r} of item {t} has color {c}"));
+            }
+            if c == -1 {
+                // Purified, and so with the item's color.
+                let h = opts.get_color(t as Link);
+                if h <= 0 {
+                    return Err(format!(
+                        "node {r} of item {t} has color -1, but item {t} is \
+                         not purified"
+                    ));
+                }
+                if h != o {
+                    return Err(format!(
+                        "node {r} of item {t} has color -1, but item {t} is \
+                         purified with {h}, not {o}"
+                    ));
+                }
+            } else if c != o {
+                return Err(format!(
+                    "node {r} of item {t} has color {c}, not {o}"
+                ));
+            }
+        }
+        if opts.ulink(q + 1) != p + 1 {
+            let u = opts.ulink(q + 1);
+            return Err(format!("ULINK({}) = {u} for spacer {}", q + 1, m + 1));
+        }
+        p = q + 1;
+    }
+    if p + 1 != len {
+        return Err(format!("{} nodes after the last spacer", len - p - 1));
+    }
+    Ok(())
+}
+
+fn check_options<O: ODance>(
+    opts: &mut O,
+    i: Link,
+    n: Count,
+) -> Result<(), String> {
+    let len = opts.nodes();
+    let mut count: Data = 0;
+    let mut x = i;
+    loop {
+        let d = opts.dlink(x);
+        if d >= len || (d != i && d <= n + 1) {
+            return Err(format!("DLINK({x}) = {d} in the list of item {i}"));
+        }
+        if opts.ulink(d) != x {
+            let u = opts.ulink(d);
+            return Err(format!("DLINK({x}) = {d} but ULINK({d}) = {u}"));
+        }
+        if d == i {
+            break;
+        }
+        if opts.top(d) != i as Data {
+            let t = opts.top(d);
+            return Err(format!("TOP({d}) = {t} in the list of item {i}"));
+        }
+        count += 1;
+        if count as Count >= len {
+            return Err(format!("the list of item {i} does not end"));
+        }
+        x = d;
+    }
+    if opts.olen(i) != count {
+        let l = opts.olen(i);
+        return Err(format!(
+            "OLEN({i}) = {l} but item {i} has {count} options"
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Problem;
+    use crate::choose::{self, KnuthTiebreak, MRVChooser};
+    use crate::links::{INodesM, ONodesC};
+    use crate::partition::SetPartition;
+    use crate::spec::taocp_xcc;
+    use crate::{dominoes, polyomino, queens};
+
+    // Checks the structures after every node of the search.
+    fn check_search<O: ODance>(problem: &mut Problem<INodesM, O>) {
+        let mut chooser =
+            MRVChooser::new(choose::no_preference(), KnuthTiebreak());
+        problem.check_invariants().unwrap();
+        loop {
+            problem.set_node_limit(problem.nodes() + 1);
+            if !problem.next_solution(&mut chooser) && !problem.is_paused() {
+                break;
+            }
+            let nodes = problem.nodes();
+            problem
+                .check_invariants()
+                .unwrap_or_else(|err| panic!("after {nodes} nodes: {err}"));
+        }
+        problem.check_invariants().unwrap();
+    }
+
+    #[test]
+    fn test_check() {
+        let spec = queens::spec(6);
+        check_search(&mut Problem::new(spec.items_m(), spec.onodes()));
+        let spec = dominoes::tiling(&polyomino::rectangle(4, 3));
+        check_search(&mut Problem::new(spec.items_m(), spec.onodes()));
+
+        let spec = taocp_xcc();
+        check_search(&mut Problem::new(spec.items_m(), spec.onodes_c()));
+
+        let mut partition = SetPartition::new(4);
+        for bits in [0b11, 0b1100, 0b1, 0b10, 0b110, 0b1000, 0b1111] {
+            partition.add_bits(bits);
+        }
+        partition.set_cover(1, 1, 2);
+        partition.set_cover(3, 0, 1);
+        let spec = partition.spec();
+        check_search(&mut Problem::new(spec.items_m(), spec.onodes()));
+    }
+
+    #[test]
+    fn test_errors() {
+        let spec = queens::spec(4);
+        let broken = |f: &dyn Fn(&mut _, &mut _)| {
+            let (mut items, mut opts) = (spec.items(), spec.onodes());
+            let colors = colors(&mut items, &mut opts);
+            f(&mut items, &mut opts);
+            check(&mut items, &mut opts, &colors).unwrap_err()
+        };
+        // Items 1..=8 are rows and columns, 9..=22 diagonals; the options
+        // start after spacer 23.
+        assert_eq!(
+            broken(&|items, _| items.set_rlink(3, 5)),
+            "RLINK(3) = 5 but LLINK(5) = 4"
+        );
+        assert_eq!(
+            broken(&|items, _| {
+                items.set_rlink(8, 9);
+                items.set_llink(9, 8);
+            }),
+            "item 9 is in the list headed by 0"
+        );
+        assert_eq!(
+            broken(&|_, opts| {
+                let len = opts.olen(2);
+                opts.set_olen(2, len + 1);
+            }),
+            "OLEN(2) = 5 but item 2 has 4 options"
+        );
+        assert_eq!(
+            broken(&|_, opts| opts.set_top(23, -1)),
+            "TOP(23) = -1 for spacer 0"
+        );
+        assert_eq!(
+            broken(&|_, opts| opts.set_ulink(28, 25)),
+            "ULINK(28) = 25 for spacer 1"
+        );
+        assert_eq!(
+            broken(&|_, opts| {
+                let d = opts.dlink(1);
+                let dd = opts.dlink(d);
+                opts.set_dlink(1, dd);
+            }),
+            "DLINK(1) = 69 but ULINK(69) = 64"
+        );
+
+        let spec = spec.colored();
+        let (mut items, mut opts) = (spec.items(), spec.onodes_c());
+        let colors = colors(&mut items, &mut opts);
+        check(&mut items, &mut opts, &colors).unwrap();
+        opts.set_color(24, 1);
+        assert_eq!(
+            check(&mut items, &mut opts, &colors),
+            Err("node 24 of item 7 has color 1".to_string())
+        );
+        assert_eq!(
+            check(&mut items, &mut opts, &colors[1..]),
+            Err("103 colors for 104 nodes".to_string())
+        );
+    }
+
+    #[test]
+    fn test_colors() {
+        // Node 10 gives item 5 (y) color 1, and node 15 gives it color 0.
+        let spec = taocp_xcc();
+        let broken = |f: &dyn Fn(&mut ONodesC)| {
+            let (mut items, mut opts) = (spec.items(), spec.onodes_c());
+            let colors = colors(&mut items, &mut opts);
+            f(&mut opts);
+            check(&mut items, &mut opts, &colors).unwrap_err()
+        };
+        assert_eq!(
+            broken(&|opts| opts.set_color(10, 2)),
+            "node 10 of item 5 has color 2, not 1"
+        );
+        assert_eq!(
+            broken(&|opts| opts.set_color(15, -1)),
+            "node 15 of item 5 has color -1, but item 5 is not purified"
+        );
+        assert_eq!(
+            broken(&|opts| {
+                opts.set_color(5, 2);
+                opts.set_color(10, -1);
+            }),
+            "node 10 of item 5 has color -1, but item 5 is purified with 2, \
+             not 1"
+        );
+
+        // While purified, colors still tells what the nodes started with.
+        let (mut items, mut opts) = (spec.items(), spec.onodes_c());
+        let before = colors(&mut items, &mut opts);
+        opts.set_color(5, 1);
+        opts.set_color(10, -1);
+        assert_eq!(colors(&mut items, &mut opts), before);
+        check(&mut items, &mut opts, &before).unwrap();
+    }
+}
@@ -1,10 +1,23 @@
+#![allow(clippy::unnecessary_cast)]
+use std::cell::{Cell, RefCell};
+
 use crate::links::INodesM;
+use crate::random::Rng;
 use crate::{Count, Data, Items, Link, ODance};
 
 pub trait Choose<I: Items> {
     fn choose<O>(&mut self, items: &mut I, opts: &mut O) -> Link
     where
         O: ODance;
+
+    // Whether to call failed. Keeping track of failures slows down every
+    // search a little, so it is left off unless a chooser asks for it.
+    const WANTS_FAILURES: bool = false;
+
+    // Called, before the next choice, for each primary item whose list of
+    // options became empty while options were being hidden, if it still
+    // had to be covered.
+    fn failed(&mut self, _i: Link) {}
 }
 
 pub trait Preference {
@@ -43,21 +56,16 @@ where
         MRVChooser { prefer, tiebreak }
     }
 
-    fn choose_mrv<I, O>(&mut self, items: &mut T::I, opts: &mut O) -> Link
+    fn choose_mrv<O>(&mut self, items: &mut T::I, opts: &mut O) -> Link
     where
         O: ODance,
     {
         let mut min = Data::MAX;
-        let mut p = *items.rlink(0);
+        let mut p = items.rlink(0);
         let mut i = p;
         let mut r: usize = 0;
         while p != 0 {
-            let olen = if self.prefer.is_preferred(p) {
-                *opts.olen(p)
-            } else {
-                *opts.olen(p) + (opts.size() as Data)
-            };
-            let curr = (olen + 1).saturating_sub(items.branch_factor(p));
+            let curr = mrv_key(&self.prefer, p, items, opts);
             if curr < min {
                 r = 1;
                 min = curr;
@@ -69,7 +77,7 @@ where
                     i = p;
                 }
             }
-            p = *items.rlink(p);
+            p = items.rlink(p);
         }
         i
     }
@@ -80,7 +88,165 @@ impl<P: Preference, T: Tiebreak> Choose<T::I> for MRVChooser<P, T> {
     where
         O: ODance,
     {
-        self.choose_mrv::<T::I, O>(items, opts)
+        self.choose_mrv(items, opts)
+    }
+}
+
+// The number of ways item p can still be covered, with items that are not
+// preferred put after all those that are.
+fn mrv_key<P, I, O>(prefer: &P, p: Link, items: &mut I, opts: &mut O) -> Data
+where
+    P: Preference,
+    I: Items,
+    O: ODance,
+{
+    let olen = if prefer.is_preferred(p) {
+        opts.olen(p)
+    } else {
+        opts.olen(p) + (opts.size() as Data)
+    };
+    (olen + 1).saturating_sub(items.branch_factor(p))
+}
+
+// Always the first active primary item, for experiments with item order.
+pub struct LeftmostChooser();
+impl<I: Items> Choose<I> for LeftmostChooser {
+    fn choose<O>(&mut self, items: &mut I, _opts: &mut O) -> Link
+    where
+        O: ODance,
+    {
+        items.rlink(0)
+    }
+}
+
+pub fn leftmost() -> LeftmostChooser {
+    LeftmostChooser()
+}
+
+// Knuth's weighted MRV: the number of ways to cover an item is divided by
+// its weight, at first 1, which goes up each time the item's list of
+// options runs out while it must still be covered. Preferred items come
+// first, and an item that cannot be covered is chosen at once.
+pub struct WeightedChooser<P>
+where
+    P: Preference,
+{
+    prefer: P,
+    weights: Vec<u64>,
+}
+
+impl<P: Preference> WeightedChooser<P> {
+    pub fn new(prefer: P) -> WeightedChooser<P> {
+        WeightedChooser { prefer, weights: Vec::new() }
+    }
+
+    // The weight of each item that has failed, by 0-based item number.
+    pub fn weights(&self) -> &[u64] {
+        self.weights.get(1..).unwrap_or(&[])
+    }
+
+    fn weight(&self, p: Link) -> u64 {
+        self.weights.get(p as usize).copied().unwrap_or(1)
+    }
+}
+
+impl<P: Preference, I: Items> Choose<I> for WeightedChooser<P> {
+    const WANTS_FAILURES: bool = true;
+
+    fn choose<O>(&mut self, items: &mut I, opts: &mut O) -> Link
+    where
+        O: ODance,
+    {
+        let mut p = items.rlink(0);
+        let mut i = p;
+        let mut best = (false, Data::MAX, 1);
+        while p != 0 {
+            let d = mrv_key(&NoPreference(), p, items, opts);
+            if d <= 0 {
+                return p;
+            }
+            let (prefer, w) = (self.prefer.is_preferred(p), self.weight(p));
+            let (best_prefer, dom, wdeg) = best;
+            // d / w < dom / wdeg
+            let less =
+                (d as u128) * (wdeg as u128) < (dom as u128) * (w as u128);
+            if (prefer && !best_prefer) || (prefer == best_prefer && less) {
+                best = (prefer, d, w);
+                i = p;
+            }
+            p = items.rlink(p);
+        }
+        i
+    }
+
+    fn failed(&mut self, i: Link) {
+        if self.weights.len() <= i as usize {
+            self.weights.resize(i as usize + 1, 1);
+        }
+        self.weights[i as usize] += 1;
+    }
+}
+
+// Fail first with learned weights ("dom/wdeg"): picks the item with the
+// smallest number of ways to cover it divided by its weight, where an
+// item's weight, at first 1, goes up each time its list runs out.
+pub struct DomWdegChooser {
+    weights: Vec<u64>,
+}
+
+impl DomWdegChooser {
+    pub fn new() -> DomWdegChooser {
+        DomWdegChooser { weights: Vec::new() }
+    }
+
+    // The weight of each item that has failed, by 0-based item number.
+    pub fn weights(&self) -> &[u64] {
+        self.weights.get(1..).unwrap_or(&[])
+    }
+
+    fn weight(&self, p: Link) -> u64 {
+        self.weights.get(p as usize).copied().unwrap_or(1)
+    }
+}
+
+impl Default for DomWdegChooser {
+    fn default() -> Self {
+        DomWdegChooser::new()
+    }
+}
+
+impl<I: Items> Choose<I> for DomWdegChooser {
+    const WANTS_FAILURES: bool = true;
+
+    fn choose<O>(&mut self, items: &mut I, opts: &mut O) -> Link
+    where
+        O: ODance,
+    {
+        let mut p = items.rlink(0);
+        let mut i = p;
+        let (mut dom, mut wdeg) = (u64::MAX, 1);
+        while p != 0 {
+            // Items that cannot be covered are certain dead ends.
+            let d = mrv_key(&NoPreference(), p, items, opts);
+            if d <= 0 {
+                return p;
+            }
+            let (d, w) = (d as u64, self.weight(p));
+            // d / w < dom / wdeg
+            if (d as u128) * (wdeg as u128) < (dom as u128) * (w as u128) {
+                (dom, wdeg) = (d, w);
+                i = p;
+            }
+            p = items.rlink(p);
+        }
+        i
+    }
+
+    fn failed(&mut self, i: Link) {
+        if self.weights.len() <= i as usize {
+            self.weights.resize(i as usize + 1, 1);
+        }
+        self.weights[i as usize] += 1;
     }
 }
 
@@ -95,6 +261,75 @@ pub fn no_preference() -> NoPreference {
     NoPreference()
 }
 
+// Prefers the items in a set.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SetPreference {
+    bits: Vec<u64>,
+}
+
+impl SetPreference {
+    // Items by 0-based number.
+    pub fn new(items: impl IntoIterator<Item = Count>) -> SetPreference {
+        let mut prefer = SetPreference::default();
+        for i in items {
+            prefer.insert(i);
+        }
+        prefer
+    }
+
+    // The items whose names start with prefix, given the names of all
+    // the items in order.
+    pub fn with_prefix<S: AsRef<str>>(
+        names: &[S],
+        prefix: &str,
+    ) -> SetPreference {
+        let items = names.iter().enumerate();
+        SetPreference::new(
+            items
+                .filter(|(_, name)| name.as_ref().starts_with(prefix))
+                .map(|(i, _)| i as Count),
+        )
+    }
+
+    pub fn insert(&mut self, i: Count) {
+        // Internal item numbers are 1-based.
+        let p = i as usize + 1;
+        if self.bits.len() <= p / 64 {
+            self.bits.resize(p / 64 + 1, 0);
+        }
+        self.bits[p / 64] |= 1 << (p % 64);
+    }
+}
+
+impl Preference for SetPreference {
+    fn is_preferred(&self, p: Link) -> bool {
+        let p = p as usize;
+        self.bits
+            .get(p / 64)
+            .is_some_and(|b| b & (1 << (p % 64)) != 0)
+    }
+}
+
+// Knuth's "sharp" preference, for items whose names start with '#'.
+pub fn sharp<S: AsRef<str>>(names: &[S]) -> SetPreference {
+    SetPreference::with_prefix(names, "#")
+}
+
+// Prefers the items for which a function of the 0-based item number
+// returns true.
+pub struct FnPreference<F>(F)
+where
+    F: Fn(Count) -> bool;
+impl<F: Fn(Count) -> bool> Preference for FnPreference<F> {
+    fn is_preferred(&self, p: Link) -> bool {
+        (self.0)(p as Count - 1)
+    }
+}
+
+pub fn prefer_fn<F: Fn(Count) -> bool>(f: F) -> FnPreference<F> {
+    FnPreference(f)
+}
+
 use std::marker::PhantomData;
 pub struct FirstWins<T>(PhantomData<T>)
 where
@@ -120,6 +355,101 @@ pub fn first_wins<T: Items>() -> FirstWins<T> {
     FirstWins(PhantomData)
 }
 
+// Picks uniformly among the items tied for the minimum, by keeping the r-th
+// one found with probability 1/r.
+pub struct RandomTiebreak<T>
+where
+    T: Items,
+{
+    rng: RefCell<Rng>,
+    items: PhantomData<T>,
+}
+
+impl<T: Items> Tiebreak for RandomTiebreak<T> {
+    type I = T;
+    fn replace<O>(
+        &self,
+        r: usize,
+        _i: Link,
+        _p: Link,
+        _items: &mut Self::I,
+        _opts: &mut O,
+    ) -> bool
+    where
+        O: ODance,
+    {
+        self.rng.borrow_mut().below(r as u64) == 0
+    }
+}
+
+pub fn random_tiebreak<T: Items>(seed: u64) -> RandomTiebreak<T> {
+    RandomTiebreak {
+        rng: RefCell::new(Rng::new(seed)),
+        items: PhantomData,
+    }
+}
+
+// Prefers items with the most nodes in their remaining options, so that
+// choosing one settles as much of the problem as possible. The degree of
+// the item ahead so far is kept, so each tied item is counted once.
+pub struct MaxDegree<T>
+where
+    T: Items,
+{
+    best: Cell<(Link, Data)>,
+    items: PhantomData<T>,
+}
+
+impl<T: Items> MaxDegree<T> {
+    fn degree<O: ODance>(i: Link, opts: &mut O) -> Data {
+        let mut degree = 0;
+        let mut p = opts.dlink(i);
+        while p != i {
+            let mut q = p;
+            while opts.top(q) > 0 {
+                q += 1;
+            }
+            // The spacer after an option points up to its first node.
+            degree += (q - opts.ulink(q)) as Data;
+            p = opts.dlink(p);
+        }
+        degree
+    }
+}
+
+impl<T: Items> Tiebreak for MaxDegree<T> {
+    type I = T;
+    fn replace<O>(
+        &self,
+        r: usize,
+        i: Link,
+        p: Link,
+        _items: &mut Self::I,
+        opts: &mut O,
+    ) -> bool
+    where
+        O: ODance,
+    {
+        // The first tie for a new minimum, r = 2, starts over.
+        let best = match self.best.get() {
+            (j, degree) if r > 2 && j == i => degree,
+            _ => Self::degree(i, opts),
+        };
+        let degree = Self::degree(p, opts);
+        if degree > best {
+            self.best.set((p, degree));
+            true
+        } else {
+            self.best.set((i, best));
+            false
+        }
+    }
+}
+
+pub fn max_degree<T: Items>() -> MaxDegree<T> {
+    MaxDegree { best: Cell::new((0, 0)), items: PhantomData }
+}
+
 pub struct KnuthTiebreak();
 impl Tiebreak for KnuthTiebreak {
     type I = INodesM;
@@ -135,7 +465,216 @@ impl Tiebreak for KnuthTiebreak {
         O: ODance,
     {
         items.slack(p) < items.slack(i)
-            || (items.slack(p) == items.slack(i)
-                && *opts.olen(p) > *opts.olen(i))
+            || (items.slack(p) == items.slack(i) && opts.olen(p) > opts.olen(i))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Problem;
+    use crate::dlx::Dlx;
+    use crate::links::{INodes, ONodes};
+    use crate::queens;
+
+    // Records the items chosen, by 0-based number.
+    struct Recorder<C> {
+        chooser: C,
+        chosen: Vec<Link>,
+        failed: Vec<Link>,
+    }
+
+    impl<I: Items, C: Choose<I>> Choose<I> for Recorder<C> {
+        const WANTS_FAILURES: bool = true;
+
+        fn choose<O>(&mut self, items: &mut I, opts: &mut O) -> Link
+        where
+            O: ODance,
+        {
+            let i = self.chooser.choose(items, opts);
+            self.chosen.push(i - 1);
+            i
+        }
+
+        fn failed(&mut self, i: Link) {
+            self.failed.push(i - 1);
+            self.chooser.failed(i);
+        }
+    }
+
+    fn first_choice<C: Choose<INodes>>(dlx: &Dlx, chooser: C) -> Link {
+        let spec = dlx.spec().uncolored().unwrap();
+        let mut problem = Problem::new(spec.items(), spec.onodes());
+        let mut recorder =
+            Recorder { chooser, chosen: Vec::new(), failed: Vec::new() };
+        problem.next_solution(&mut recorder);
+        recorder.chosen[0]
+    }
+
+    fn count<C: Choose<INodes>>(
+        problem: (INodes, ONodes),
+        mut chooser: C,
+    ) -> u64 {
+        Problem::new(problem.0, problem.1).count_solutions(&mut chooser)
+    }
+
+    #[test]
+    fn test_choosers() {
+        let q = || queens::problem(8);
+        assert_eq!(count(q(), leftmost()), 92);
+        assert_eq!(
+            count(q(), MRVChooser::new(no_preference(), max_degree())),
+            92
+        );
+        let mut weighted = WeightedChooser::new(no_preference());
+        let (items, opts) = q();
+        let mut problem = Problem::new(items, opts);
+        assert_eq!(problem.count_solutions(&mut weighted), 92);
+        assert!(weighted.weights().iter().any(|&w| w > 1));
+        assert!(weighted.weights().len() <= 16);
+    }
+
+    fn nodes<C: Choose<INodesM>>(dlx: &Dlx, mut chooser: C) -> u64 {
+        let spec = dlx.spec().uncolored().unwrap();
+        let mut problem = Problem::new(spec.items_m(), spec.onodes());
+        problem.count_solutions(&mut chooser);
+        problem.nodes()
+    }
+
+    #[test]
+    fn test_weighted() {
+        // Pairs cannot cover b1, b2 and b3 exactly, but the a items have
+        // fewer options, so MRV tries every way of covering them first.
+        // Once the b items have failed often enough to outweigh that,
+        // they are chosen first instead.
+        let mut input = String::from("a0 a1 a2 a3 a4 a5 a6 a7 b1 b2 b3\n");
+        for a in 0..8 {
+            input += &format!("a{a}\na{a}\n");
+        }
+        input += "b1 b2\nb1 b2\nb2 b3\nb2 b3\nb1 b3\nb1 b3";
+        let dlx = Dlx::parse(&input).unwrap();
+        let mrv = nodes(&dlx, MRVChooser::new(no_preference(), first_wins()));
+        let weighted = nodes(&dlx, WeightedChooser::new(no_preference()));
+        assert_eq!((mrv, weighted), (1535, 53));
+    }
+
+    #[test]
+    fn test_dead_end_first() {
+        // a must be covered three times but has one option, so MRV gives
+        // up at once. dom/wdeg must too, rather than trying b and c first.
+        let dlx = Dlx::parse("b c 3|a\na\nb\nc\nb c\nb\nc").unwrap();
+        let mrv = nodes(&dlx, MRVChooser::new(no_preference(), first_wins()));
+        assert_eq!((mrv, nodes(&dlx, DomWdegChooser::new())), (1, 1));
+    }
+
+    #[test]
+    fn test_failed() {
+        // Choosing a then "a" leaves nothing for b.
+        let dlx = Dlx::parse("a b\na b\na").unwrap();
+        let spec = dlx.spec().uncolored().unwrap();
+        let mut problem = Problem::new(spec.items(), spec.onodes());
+        let mut recorder = Recorder {
+            chooser: leftmost(),
+            chosen: Vec::new(),
+            failed: Vec::new(),
+        };
+        assert_eq!(problem.count_solutions(&mut recorder), 1);
+        assert_eq!(recorder.chosen, [0, 1]);
+        assert_eq!(recorder.failed, [1]);
+
+        // a may be left out, so it has not failed when "b" hides its only
+        // option.
+        let dlx = Dlx::parse("b 0:1|a\na b\nb").unwrap();
+        let spec = dlx.spec().uncolored().unwrap();
+        let mut problem = Problem::new(spec.items_m(), spec.onodes());
+        let mut recorder = Recorder {
+            chooser: leftmost(),
+            chosen: Vec::new(),
+            failed: Vec::new(),
+        };
+        assert_eq!(problem.count_solutions(&mut recorder), 2);
+        assert_eq!(recorder.chosen, [0, 1]);
+        assert!(recorder.failed.is_empty());
+
+        let mut dom_wdeg = DomWdegChooser::new();
+        let (items, opts) = queens::problem(8);
+        let mut problem = Problem::new(items, opts);
+        assert_eq!(problem.count_solutions(&mut dom_wdeg), 92);
+        let weights = dom_wdeg.weights();
+        assert!(!weights.is_empty() && weights.iter().all(|&w| w >= 1));
+        assert!(weights.iter().any(|&w| w > 1));
+    }
+
+    #[test]
+    fn test_first_choice() {
+        // a has the fewest options, and #c is sharp.
+        let dlx = Dlx::parse(
+            "b a #c d
+             a b
+             a #c d
+             b #c d
+             b d
+             #c d",
+        )
+        .unwrap();
+        let mrv = || MRVChooser::new(no_preference(), first_wins());
+        assert_eq!(first_choice(&dlx, mrv()), 1);
+        assert_eq!(first_choice(&dlx, leftmost()), 0);
+        let sharp = sharp(dlx.items());
+        assert!(!sharp.is_preferred(1) && sharp.is_preferred(3));
+        let chooser = MRVChooser::new(sharp, first_wins());
+        assert_eq!(first_choice(&dlx, chooser), 2);
+
+        // a and b both have two options, with three nodes for a and five
+        // for b.
+        let dlx = Dlx::parse(
+            "a b c d e
+             a
+             a c
+             b c d e
+             b
+             c d e
+             d e",
+        )
+        .unwrap();
+        assert_eq!(first_choice(&dlx, mrv()), 0);
+        let chooser = MRVChooser::new(no_preference(), max_degree());
+        assert_eq!(first_choice(&dlx, chooser), 1);
+    }
+
+    fn order<P: Preference>(problem: (INodes, ONodes), prefer: P) -> Vec<Link> {
+        let mut recorder = Recorder {
+            chooser: MRVChooser::new(prefer, first_wins()),
+            chosen: Vec::new(),
+            failed: Vec::new(),
+        };
+        let mut problem = Problem::new(problem.0, problem.1);
+        assert_eq!(problem.count_solutions(&mut recorder), 4);
+        recorder.chosen
+    }
+
+    #[test]
+    fn test_preferences() {
+        // Rows of the 6 x 6 board are even items and columns odd ones.
+        let q = || queens::problem(6);
+        let rows = SetPreference::new((0..6).map(|r| 2 * r));
+        assert!(rows.is_preferred(1) && !rows.is_preferred(2));
+        assert!(!rows.is_preferred(64) && !rows.is_preferred(1000));
+        let by_rows = order(q(), rows);
+        assert!(by_rows.iter().all(|&i| i % 2 == 0));
+        assert_eq!(order(q(), prefer_fn(|i| i % 2 == 0)), by_rows);
+
+        let by_columns = order(q(), prefer_fn(|i| i % 2 == 1));
+        assert!(by_columns.iter().all(|&i| i % 2 == 1));
+        // Without a preference, MRV mixes rows and columns.
+        let mixed = order(q(), no_preference());
+        assert!(mixed.iter().any(|&i| i % 2 == 0));
+        assert!(mixed.iter().any(|&i| i % 2 == 1));
+
+        let names = ["r0", "c0", "r1", "c1", "x"];
+        assert_eq!(
+            SetPreference::with_prefix(&names, "c"),
+            SetPreference::new([1, 3])
+        );
     }
 }
@@ -0,0 +1,256 @@
+#![allow(clippy::unnecessary_cast)]
+use crate::choose::{self, FirstWins, MRVChooser, NoPreference};
+use crate::links::{INodes, ONodesC};
+use crate::spec::Spec;
+use crate::{Count, Data, Problem};
+
+// The vertex and color chosen by an option.
+type Move = (usize, usize);
+
+// Colorings of a graph with colors 0..colors, where adjacent vertices get
+// different colors. Each vertex may be limited to a list of colors.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Coloring {
+    edges: Vec<(usize, usize)>,
+    allowed: Vec<Vec<usize>>,
+    colors: usize,
+    up_to_permutation: bool,
+}
+
+impl Coloring {
+    // Edges may be listed from either end, or both.
+    pub fn new(adjacency: &[Vec<usize>], colors: usize) -> Coloring {
+        let n = adjacency.len();
+        let mut edges = Vec::new();
+        for (u, adj) in adjacency.iter().enumerate() {
+            for &v in adj {
+                assert!(v < n, "vertex out of range");
+                assert!(u != v, "loops cannot be colored");
+                edges.push((u.min(v), u.max(v)));
+            }
+        }
+        edges.sort();
+        edges.dedup();
+        Coloring {
+            edges,
+            allowed: vec![(0..colors).collect(); n],
+            colors,
+            up_to_permutation: false,
+        }
+    }
+
+    pub fn vertices(&self) -> usize {
+        self.allowed.len()
+    }
+
+    pub fn edges(&self) -> &[(usize, usize)] {
+        &self.edges
+    }
+
+    // Limits vertex v to the given colors (list coloring).
+    pub fn set_allowed(&mut self, v: usize, colors: &[usize]) {
+        assert!(colors.iter().all(|&c| c < self.colors), "no such color");
+        let mut colors = colors.to_vec();
+        colors.sort();
+        colors.dedup();
+        self.allowed[v] = colors;
+    }
+
+    // Finds one coloring from each class of colorings that differ only by
+    // renaming the colors: the one whose colors first appear in order
+    // 0, 1, 2, ... going through the vertices. Needs every vertex to allow
+    // every color.
+    pub fn set_up_to_permutation(&mut self, up_to_permutation: bool) {
+        self.up_to_permutation = up_to_permutation;
+    }
+
+    pub fn spec(&self) -> Spec<(Count, Data)> {
+        self.build().0
+    }
+
+    // The color of each vertex.
+    pub fn solutions(&self) -> Solutions {
+        let (spec, moves) = self.build();
+        Solutions {
+            problem: Problem::new(spec.items(), spec.onodes_c()),
+            chooser: MRVChooser::new(
+                choose::no_preference(),
+                choose::first_wins(),
+            ),
+            moves,
+            vertices: self.vertices(),
+            done: false,
+        }
+    }
+
+    // Vertices are primary items, with one option per allowed color. Each
+    // edge has a secondary item for each color, in the options that give
+    // that color to either end, so adjacent vertices cannot share it. The
+    // size of the problem is linear in the number of edges.
+    //
+    // Up to permutation, secondary item U(v) is colored by the number of
+    // colors used by vertices 0..=v. Vertex v may take any color c <= u,
+    // where U(v - 1) = u (0 before vertex 0), which makes U(v) = max(u,
+    // c + 1); so the colors first appear in order. Each (u, c) is a
+    // separate option.
+    //
+    // Also returns the (vertex, color) chosen by each option.
+    fn build(&self) -> (Spec<(Count, Data)>, Vec<Move>) {
+        let n = self.vertices();
+        let k = self.colors;
+        if self.up_to_permutation {
+            let all: Vec<usize> = (0..k).collect();
+            assert!(self.allowed.iter().all(|a| *a == all), "list coloring");
+        }
+        let m = self.edges.len() * k;
+        let ns = m + if self.up_to_permutation { n } else { 0 };
+        let edge = |e: usize, c: usize| (n + e * k + c) as Count;
+        let used = |v: usize| (n + m + v) as Count;
+        let mut incident = vec![Vec::new(); n];
+        for (e, &(u, v)) in self.edges.iter().enumerate() {
+            incident[u].push(e);
+            incident[v].push(e);
+        }
+
+        let mut spec = Spec::new(n as Count, ns as Count);
+        let mut moves = Vec::new();
+        for (v, edges) in incident.iter().enumerate() {
+            for &c in &self.allowed[v] {
+                let mut opt = vec![(v as Count, 0)];
+                opt.extend(edges.iter().map(|&e| (edge(e, c), 0)));
+                if !self.up_to_permutation {
+                    spec.add_option(opt);
+                    moves.push((v, c));
+                    continue;
+                }
+                for u in c.max(v.min(1))..=v.min(k) {
+                    let mut opt = opt.clone();
+                    if v > 0 {
+                        opt.push((used(v - 1), u as Data));
+                    }
+                    opt.push((used(v), u.max(c + 1) as Data));
+                    spec.add_option(opt);
+                    moves.push((v, c));
+                }
+            }
+        }
+        (spec, moves)
+    }
+}
+
+pub struct Solutions {
+    problem: Problem<INodes, ONodesC>,
+    chooser: MRVChooser<NoPreference, FirstWins<INodes>>,
+    moves: Vec<Move>,
+    vertices: usize,
+    done: bool,
+}
+
+impl Iterator for Solutions {
+    type Item = Vec<usize>;
+
+    fn next(&mut self) -> Option<Vec<usize>> {
+        if self.done || !self.problem.next_solution(&mut self.chooser) {
+            self.done = true;
+            return None;
+        }
+        self.problem.find_options();
+        let mut colors = vec![0; self.vertices];
+        for &k in self.problem.get_options() {
+            let (v, c) = self.moves[k as usize];
+            colors[v] = c;
+        }
+        Some(colors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cycle(n: usize) -> Vec<Vec<usize>> {
+        (0..n).map(|v| vec![(v + 1) % n]).collect()
+    }
+
+    fn petersen() -> Vec<Vec<usize>> {
+        (0..10)
+            .map(|v| {
+                if v < 5 {
+                    vec![(v + 1) % 5, v + 5]
+                } else {
+                    vec![5 + (v + 2) % 5]
+                }
+            })
+            .collect()
+    }
+
+    fn check(coloring: &Coloring, expected: usize) {
+        let mut all = Vec::new();
+        for colors in coloring.solutions() {
+            for &(u, v) in coloring.edges() {
+                assert_ne!(colors[u], colors[v], "edge {u}-{v}");
+            }
+            for (v, &c) in colors.iter().enumerate() {
+                assert!(coloring.allowed[v].contains(&c));
+            }
+            all.push(colors);
+        }
+        assert_eq!(all.len(), expected);
+        all.sort();
+        all.dedup();
+        assert_eq!(all.len(), expected, "repeated colorings");
+        if coloring.up_to_permutation {
+            for colors in all {
+                let mut used = 0;
+                for c in colors {
+                    assert!(c <= used, "colors out of order");
+                    used = used.max(c + 1);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_coloring() {
+        // k(k - 1)(k - 2) for a triangle, (k - 1)^n + (-1)^n (k - 1) for a
+        // cycle; the Petersen graph has 120 3-colorings.
+        for (graph, k, expected, classes) in [
+            (cycle(3), 3, 6, 1),
+            (cycle(3), 4, 24, 1),
+            (cycle(5), 3, 30, 5),
+            (cycle(6), 2, 2, 1),
+            (cycle(7), 2, 0, 0),
+            (petersen(), 3, 120, 20),
+        ] {
+            let mut coloring = Coloring::new(&graph, k);
+            check(&coloring, expected);
+            coloring.set_up_to_permutation(true);
+            check(&coloring, classes);
+        }
+        let mut coloring = Coloring::new(&cycle(5), 3);
+        assert_eq!(coloring.edges().len(), 5);
+        let spec = coloring.spec();
+        assert_eq!((spec.primary(), spec.secondary()), (5, 5 * 3));
+        assert_eq!((spec.size(), spec.nodes()), (5 * 3, 5 * 3 * 3));
+        // Vertex v > 0 has an option for each 1 <= u <= min(v, 3) and
+        // c <= min(u, 2); vertex 0 has just one.
+        coloring.set_up_to_permutation(true);
+        let spec = coloring.spec();
+        assert_eq!(spec.secondary(), 5 * 3 + 5);
+        assert_eq!(spec.size(), 1 + 2 + 5 + 8 + 8);
+    }
+
+    #[test]
+    fn test_list_coloring() {
+        // A path a - b - c where a may only be 0 and c only 1.
+        let path = vec![vec![1], vec![2], vec![]];
+        let mut coloring = Coloring::new(&path, 3);
+        coloring.set_allowed(0, &[0]);
+        coloring.set_allowed(2, &[1]);
+        check(&coloring, 1);
+        assert_eq!(coloring.solutions().next(), Some(vec![0, 2, 1]));
+
+        coloring.set_allowed(1, &[0, 1]);
+        check(&coloring, 0);
+    }
+}
@@ -0,0 +1,221 @@
+#![allow(clippy::unnecessary_cast)]
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::hash::Hash;
+
+use crate::choose::{self, FirstWins, MRVChooser, NoPreference};
+use crate::links::{INodes, ONodes};
+use crate::spec::Spec;
+use crate::{Count, Problem};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Item {
+    Primary(usize),
+    Secondary(usize),
+}
+
+// An exact cover problem over user item types. Options carry a payload,
+// and solutions are returned as the payloads of the chosen options.
+#[derive(Clone, Debug)]
+pub struct ExactCover<K, T> {
+    primary: Vec<K>,
+    secondary: Vec<K>,
+    index: HashMap<K, Item>,
+    options: Vec<(T, Vec<Item>)>,
+}
+
+impl<K, T> Default for ExactCover<K, T> {
+    fn default() -> Self {
+        ExactCover {
+            primary: Vec::new(),
+            secondary: Vec::new(),
+            index: HashMap::new(),
+            options: Vec::new(),
+        }
+    }
+}
+
+impl<K: Hash + Eq + Clone, T> ExactCover<K, T> {
+    pub fn new() -> ExactCover<K, T> {
+        Default::default()
+    }
+
+    pub fn add_primary(&mut self, k: K) {
+        self.item(k);
+    }
+
+    pub fn add_secondary(&mut self, k: K) {
+        match self.index.entry(k) {
+            Entry::Occupied(e) => {
+                assert!(
+                    matches!(e.get(), Item::Secondary(_)),
+                    "item already used as primary"
+                );
+            }
+            Entry::Vacant(e) => {
+                self.secondary.push(e.key().clone());
+                e.insert(Item::Secondary(self.secondary.len() - 1));
+            }
+        }
+    }
+
+    // Items that have not been declared are added as primary items. An
+    // option may not list the same item twice.
+    pub fn add_option(
+        &mut self,
+        payload: T,
+        items: impl IntoIterator<Item = K>,
+    ) {
+        let items: Vec<Item> =
+            items.into_iter().map(|k| self.item(k)).collect();
+        for (a, i) in items.iter().enumerate() {
+            assert!(!items[..a].contains(i), "item repeated in an option");
+        }
+        self.options.push((payload, items));
+    }
+
+    pub fn primary(&self) -> &[K] {
+        &self.primary
+    }
+
+    pub fn secondary(&self) -> &[K] {
+        &self.secondary
+    }
+
+    pub fn payloads(&self) -> impl Iterator<Item = &T> {
+        self.options.iter().map(|o| &o.0)
+    }
+
+    // The numeric problem; option k is the k-th option added.
+    pub fn spec(&self) -> Spec<Count> {
+        let np = self.primary.len();
+        let mut spec = Spec::new(np as Count, self.secondary.len() as Count);
+        for (_, items) in &self.options {
+            spec.add_option(items.iter().map(|&i| match i {
+                Item::Primary(i) => i as Count,
+                Item::Secondary(i) => (np + i) as Count,
+            }));
+        }
+        spec
+    }
+
+    pub fn solutions(&self) -> Solutions<'_, T> {
+        let spec = self.spec();
+        Solutions {
+            options: &self.options,
+            problem: Problem::new(spec.items(), spec.onodes()),
+            chooser: MRVChooser::new(
+                choose::no_preference(),
+                choose::first_wins(),
+            ),
+            done: false,
+        }
+    }
+
+    fn item(&mut self, k: K) -> Item {
+        match self.index.entry(k) {
+            Entry::Occupied(e) => *e.get(),
+            Entry::Vacant(e) => {
+                self.primary.push(e.key().clone());
+                *e.insert(Item::Primary(self.primary.len() - 1))
+            }
+        }
+    }
+}
+
+pub struct Solutions<'a, T> {
+    options: &'a [(T, Vec<Item>)],
+    problem: Problem<INodes, ONodes>,
+    chooser: MRVChooser<NoPreference, FirstWins<INodes>>,
+    done: bool,
+}
+
+impl<'a, T> Iterator for Solutions<'a, T> {
+    type Item = Vec<&'a T>;
+
+    fn next(&mut self) -> Option<Vec<&'a T>> {
+        if self.done || !self.problem.next_solution(&mut self.chooser) {
+            self.done = true;
+            return None;
+        }
+        self.problem.find_options();
+        let options = self.options;
+        Some(
+            self.problem
+                .get_options()
+                .iter()
+                .map(|&k| &options[k as usize].0)
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+    enum Cell {
+        Piece(char),
+        Square(i32, i32),
+    }
+
+    #[test]
+    // TAocp Vol. 4B p. 66
+    fn test_xc() {
+        let mut cover = ExactCover::new();
+        for (name, items) in [
+            ("ce", "ce"),
+            ("adg", "adg"),
+            ("bcf", "bcf"),
+            ("adf", "adf"),
+            ("bg", "bg"),
+            ("deg", "deg"),
+        ] {
+            cover.add_option(name, items.chars());
+        }
+        assert_eq!(cover.primary().len(), 7);
+        let solutions: Vec<Vec<&&str>> = cover.solutions().collect();
+        let mut solution = solutions[0].clone();
+        solution.sort();
+        assert_eq!(solutions.len(), 1);
+        assert_eq!(solution, [&"adf", &"bg", &"ce"]);
+    }
+
+    #[test]
+    fn test_secondary() {
+        // A domino and a monomino on a 1x3 strip, with the middle square
+        // declared before use as a secondary item.
+        let mut cover = ExactCover::new();
+        cover.add_secondary(Cell::Square(0, 1));
+        cover.add_primary(Cell::Square(0, 0));
+        for x in 0..2 {
+            let squares = [Cell::Square(0, x), Cell::Square(0, x + 1)];
+            cover.add_option(
+                ('D', x),
+                std::iter::once(Cell::Piece('D')).chain(squares),
+            );
+        }
+        for x in 0..3 {
+            cover.add_option(('M', x), [Cell::Piece('M'), Cell::Square(0, x)]);
+        }
+        assert_eq!(cover.secondary(), [Cell::Square(0, 1)]);
+        let mut solutions: Vec<Vec<(char, i32)>> = cover
+            .solutions()
+            .map(|s| {
+                let mut s: Vec<(char, i32)> = s.into_iter().copied().collect();
+                s.sort();
+                s
+            })
+            .collect();
+        solutions.sort();
+        assert_eq!(solutions, [[('D', 0), ('M', 2)], [('D', 1), ('M', 0)]]);
+    }
+
+    #[test]
+    #[should_panic(expected = "item repeated in an option")]
+    fn test_repeated() {
+        let mut cover = ExactCover::new();
+        cover.add_option("aa", "aab".chars());
+    }
+}
@@ -0,0 +1,281 @@
+#![allow(clippy::unnecessary_cast)]
+use crate::choose::{self, FirstWins, MRVChooser, NoPreference};
+use crate::links::{INodes, ONodesC};
+use crate::spec::Spec;
+use crate::{Count, Data, Problem};
+
+// The (slot, word) placed by an option.
+type Placement = (usize, usize);
+
+// A grid to be filled with words. In the pattern, '#' is a black square,
+// '.' an empty square and anything else a given letter.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Crossword {
+    width: usize,
+    grid: Vec<Option<char>>,
+    white: Vec<bool>,
+    slots: Vec<Vec<usize>>,
+    distinct: bool,
+}
+
+impl Crossword {
+    pub fn new(pattern: &str) -> Crossword {
+        let rows: Vec<Vec<char>> = pattern
+            .lines()
+            .map(|l| l.trim())
+            .filter(|l| !l.is_empty())
+            .map(|l| l.chars().collect())
+            .collect();
+        let width = rows.first().map_or(0, |r| r.len());
+        assert!(width > 0, "empty grid");
+        assert!(rows.iter().all(|r| r.len() == width), "ragged grid");
+        let cells: Vec<char> = rows.concat();
+        let grid = cells
+            .iter()
+            .map(|&ch| (ch != '#' && ch != '.').then_some(ch))
+            .collect();
+        let white: Vec<bool> = cells.iter().map(|&ch| ch != '#').collect();
+
+        // Slots are the runs of two or more white squares, across then
+        // down.
+        let height = cells.len() / width;
+        let mut slots = Vec::new();
+        let mut runs =
+            |lines: usize, len: usize, at: &dyn Fn(usize, usize) -> usize| {
+                for i in 0..lines {
+                    let mut run = Vec::new();
+                    for j in 0..=len {
+                        let cell = (j < len).then(|| at(i, j));
+                        match cell {
+                            Some(cell) if white[cell] => run.push(cell),
+                            _ => {
+                                if run.len() > 1 {
+                                    slots.push(run.clone());
+                                }
+                                run.clear();
+                            }
+                        }
+                    }
+                }
+            };
+        runs(height, width, &|r, c| r * width + c);
+        runs(width, height, &|c, r| r * width + c);
+        Crossword { width, grid, white, slots, distinct: false }
+    }
+
+    // An empty w x h grid, to be filled by words reading across and down.
+    pub fn rectangle(w: usize, h: usize) -> Crossword {
+        Crossword::new(&vec![".".repeat(w); h].join("\n"))
+    }
+
+    // The squares of each slot, numbered row by row.
+    pub fn slots(&self) -> &[Vec<usize>] {
+        &self.slots
+    }
+
+    // No word may be used in more than one slot.
+    pub fn set_distinct_words(&mut self, distinct: bool) {
+        self.distinct = distinct;
+    }
+
+    // Slots are primary items. Every white square is a secondary item,
+    // colored by the letter placed in it, and with distinct words each word
+    // is an uncolored secondary item too. The options put each word that
+    // fits into each slot, slot by slot.
+    pub fn spec(&self, words: &[&str]) -> Spec<(Count, Data)> {
+        self.build(words).0
+    }
+
+    // Filled grids, one string per row.
+    pub fn solutions(&self, words: &[&str]) -> Solutions {
+        let (spec, placements) = self.build(words);
+        Solutions {
+            problem: Problem::new(spec.items(), spec.onodes_c()),
+            chooser: MRVChooser::new(
+                choose::no_preference(),
+                choose::first_wins(),
+            ),
+            crossword: self.clone(),
+            words: words.iter().map(|w| w.chars().collect()).collect(),
+            placements,
+            done: false,
+        }
+    }
+
+    pub fn render(&self, letters: &[Option<char>]) -> Vec<String> {
+        letters
+            .chunks(self.width)
+            .zip(self.white.chunks(self.width))
+            .map(|(row, white)| {
+                row.iter()
+                    .zip(white)
+                    .map(|(&ch, &w)| match ch {
+                        Some(ch) => ch,
+                        None if w => '.',
+                        None => '#',
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    // Also returns the placement made by each option.
+    fn build(&self, words: &[&str]) -> (Spec<(Count, Data)>, Vec<Placement>) {
+        let np = self.slots.len();
+        let mut square = vec![None; self.grid.len()];
+        let mut ns = 0;
+        for (cell, &white) in self.white.iter().enumerate() {
+            if white {
+                square[cell] = Some(np + ns);
+                ns += 1;
+            }
+        }
+        let word0 = np + ns;
+        if self.distinct {
+            ns += words.len();
+        }
+
+        let mut spec = Spec::new(np as Count, ns as Count);
+        let mut placements = Vec::new();
+        for (s, slot) in self.slots.iter().enumerate() {
+            for (w, word) in words.iter().enumerate() {
+                let letters: Vec<char> = word.chars().collect();
+                if letters.len() != slot.len() {
+                    continue;
+                }
+                let fits = slot.iter().zip(&letters).all(|(&cell, &ch)| {
+                    self.grid[cell].is_none_or(|given| given == ch)
+                });
+                if !fits {
+                    continue;
+                }
+                let mut opt = vec![(s as Count, 0)];
+                for (&cell, &ch) in slot.iter().zip(&letters) {
+                    opt.push((square[cell].unwrap() as Count, ch as Data));
+                }
+                if self.distinct {
+                    opt.push(((word0 + w) as Count, 0));
+                }
+                spec.add_option(opt);
+                placements.push((s, w));
+            }
+        }
+        (spec, placements)
+    }
+}
+
+pub struct Solutions {
+    problem: Problem<INodes, ONodesC>,
+    chooser: MRVChooser<NoPreference, FirstWins<INodes>>,
+    crossword: Crossword,
+    words: Vec<Vec<char>>,
+    placements: Vec<Placement>,
+    done: bool,
+}
+
+impl Iterator for Solutions {
+    type Item = Vec<String>;
+
+    fn next(&mut self) -> Option<Vec<String>> {
+        if self.done || !self.problem.next_solution(&mut self.chooser) {
+            self.done = true;
+            return None;
+        }
+        self.problem.find_options();
+        let mut letters = self.crossword.grid.clone();
+        for &k in self.problem.get_options() {
+            let (s, w) = self.placements[k as usize];
+            for (&cell, &ch) in
+                self.crossword.slots[s].iter().zip(&self.words[w])
+            {
+                letters[cell] = Some(ch);
+            }
+        }
+        Some(self.crossword.render(&letters))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WORDS: [&str; 12] = [
+        "ace", "act", "arc", "are", "bar", "car", "cat", "ear", "eat", "era",
+        "rat", "tea",
+    ];
+
+    fn columns(rows: &[String]) -> Vec<String> {
+        let rows: Vec<Vec<char>> =
+            rows.iter().map(|r| r.chars().collect()).collect();
+        (0..rows[0].len())
+            .map(|c| rows.iter().map(|r| r[c]).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_rectangle() {
+        // Every choice of rows whose columns are also words.
+        let mut expected = Vec::new();
+        for a in WORDS {
+            for b in WORDS {
+                for c in WORDS {
+                    let rows = [a, b, c].map(String::from);
+                    if columns(&rows)
+                        .iter()
+                        .all(|w| WORDS.contains(&w.as_str()))
+                    {
+                        expected.push(rows.to_vec());
+                    }
+                }
+            }
+        }
+        expected.sort();
+        assert!(!expected.is_empty());
+
+        let square = Crossword::rectangle(3, 3);
+        assert_eq!(square.slots().len(), 6);
+        let mut found: Vec<Vec<String>> = square.solutions(&WORDS).collect();
+        found.sort();
+        assert_eq!(found, expected);
+
+        // Without symmetric squares, whose rows and columns are the same.
+        let mut distinct = square.clone();
+        distinct.set_distinct_words(true);
+        let mut found: Vec<Vec<String>> = distinct.solutions(&WORDS).collect();
+        found.sort();
+        expected.retain(|rows| {
+            let mut all = [rows.clone(), columns(rows)].concat();
+            all.sort();
+            all.windows(2).all(|w| w[0] != w[1])
+        });
+        assert_eq!(found, expected);
+    }
+
+    #[test]
+    fn test_crossword() {
+        let mut crossword = Crossword::new(
+            "c.#
+             ...
+             #..",
+        );
+        assert_eq!(crossword.slots().len(), 6);
+        let words = ["ca", "are", "ta", "art", "ea", "car", "at", "to", "ear"];
+        let mut found: Vec<Vec<String>> = crossword.solutions(&words).collect();
+        found.sort();
+        assert_eq!(
+            found,
+            [
+                ["ca#", "are", "#ea"],
+                ["ca#", "are", "#ta"],
+                ["ca#", "art", "#ea"],
+                ["ca#", "art", "#ta"],
+                ["ca#", "art", "#to"],
+            ]
+        );
+        assert_eq!(crossword.spec(&words).primary(), 6);
+
+        // The first row and column both have to be "ca".
+        crossword.set_distinct_words(true);
+        assert!(crossword.solutions(&words).next().is_none());
+    }
+}
@@ -0,0 +1,363 @@
+#![allow(clippy::unnecessary_cast)]
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+use crate::spec::Spec;
+use crate::{Choice, Count, Data};
+
+// A problem in the input format of Knuth's DLX programs. Lines starting
+// with '|' are comments. The first line names the items: primary items,
+// then '|', then secondary items. A primary item written "u:v|name" or
+// "v|name" is covered between u and v times (v times if u is left out).
+// Every other line is an option, listing the names of its items; a
+// secondary item may be written "name:color".
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Dlx {
+    items: Vec<String>,
+    primary: usize,
+    colors: Vec<String>,
+    options: Vec<String>,
+    spec: Spec<(Count, Data)>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ParseError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for ParseError {}
+
+impl Dlx {
+    pub fn parse(input: &str) -> Result<Dlx, ParseError> {
+        let mut lines = input
+            .lines()
+            .enumerate()
+            .map(|(n, line)| (n + 1, line.trim()))
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with('|'));
+        let error = |line: usize, message: String| ParseError { line, message };
+
+        let Some((n, first)) = lines.next() else {
+            return Err(error(0, "no items".to_string()));
+        };
+        let mut items = Vec::new();
+        let mut bounds = Vec::new();
+        let mut primary = None;
+        for token in first.split_whitespace() {
+            if token == "|" {
+                if primary.is_some() {
+                    return Err(error(n, "'|' appears twice".to_string()));
+                }
+                primary = Some(items.len());
+                continue;
+            }
+            let (name, bound) = match token.rsplit_once('|') {
+                Some((bound, name)) => (name, Some(bound)),
+                None => (token, None),
+            };
+            if name.is_empty() || name.contains(':') {
+                return Err(error(n, format!("bad item name \"{token}\"")));
+            }
+            if let Some(bound) = bound {
+                if primary.is_some() {
+                    let message =
+                        format!("secondary item \"{name}\" has bounds");
+                    return Err(error(n, message));
+                }
+                let bound = parse_bounds(bound).ok_or_else(|| {
+                    error(n, format!("bad multiplicity \"{token}\""))
+                })?;
+                bounds.push((items.len(), bound));
+            }
+            items.push(name.to_string());
+        }
+        // The line cannot start with '|', so there is a primary item.
+        let primary = primary.unwrap_or(items.len());
+        let mut index = HashMap::new();
+        for (i, name) in items.iter().enumerate() {
+            if index.insert(name.as_str(), i).is_some() {
+                return Err(error(n, format!("item \"{name}\" appears twice")));
+            }
+        }
+
+        let mut spec =
+            Spec::new(primary as Count, (items.len() - primary) as Count);
+        for (i, (u, v)) in bounds {
+            spec.set_bounds(i as Count, u, v);
+        }
+        let mut colors = Vec::new();
+        let mut options = Vec::new();
+        for (n, line) in lines {
+            let mut opt = Vec::new();
+            for token in line.split_whitespace() {
+                let (name, color) = match token.split_once(':') {
+                    Some((name, color)) => (name, Some(color)),
+                    None => (token, None),
+                };
+                let Some(&i) = index.get(name) else {
+                    return Err(error(n, format!("unknown item \"{name}\"")));
+                };
+                if opt.iter().any(|&(j, _)| j == i as Count) {
+                    return Err(error(n, format!("item \"{name}\" repeated")));
+                }
+                let c = match color {
+                    None => 0,
+                    Some(_) if i < primary => {
+                        let message =
+                            format!("primary item \"{name}\" has a color");
+                        return Err(error(n, message));
+                    }
+                    Some("") => {
+                        let message = format!("empty color in \"{token}\"");
+                        return Err(error(n, message));
+                    }
+                    Some(color) => intern(&mut colors, color),
+                };
+                opt.push((i as Count, c as Data));
+            }
+            spec.add_option(opt);
+            options.push(line.to_string());
+        }
+        Ok(Dlx { items, primary, colors, options, spec })
+    }
+
+    pub fn spec(&self) -> &Spec<(Count, Data)> {
+        &self.spec
+    }
+
+    // All item names, primary items first.
+    pub fn items(&self) -> &[String] {
+        &self.items
+    }
+
+    pub fn primary(&self) -> usize {
+        self.primary
+    }
+
+    // The name of color c, for c > 0.
+    pub fn color(&self, c: Data) -> &str {
+        &self.colors[c as usize - 1]
+    }
+
+    // Option k as it was written.
+    pub fn option(&self, k: usize) -> &str {
+        &self.options[k]
+    }
+
+    // Option k with the names of its items in order, single spaced, each
+    // colored item followed by ":color".
+    pub fn format_option(&self, k: usize) -> String {
+        self.format_items(k, 0)
+    }
+
+    // One level of a solution as print_option in Knuth's DLX2 writes it,
+    // without the leading space: the option's items, starting from the
+    // item branched on and going round, then " (k of d)" if the option was
+    // the k-th of the d the item had left, or " (?)". A level where an item
+    // took no option is written as "null" and the item.
+    pub fn format_choice(&self, choice: &Choice) -> String {
+        let Some(k) = choice.option else {
+            return format!("null {}", self.items[choice.item as usize]);
+        };
+        let mut s = self.format_items(k, choice.node);
+        match choice.place {
+            Some(k) => s.push_str(&format!(" ({k} of {})", choice.len)),
+            None => s.push_str(" (?)"),
+        }
+        s
+    }
+
+    // The choices of a solution, from Problem::find_choices, one per line.
+    // Each primary item that may be covered a varying number of times then
+    // gets a comment line saying how often it was covered.
+    pub fn format_solution(&self, choices: &[Choice]) -> String {
+        let mut s = String::new();
+        for choice in choices {
+            s.push_str(&self.format_choice(choice));
+            s.push('\n');
+        }
+        for i in 0..self.primary {
+            let (u, v) = self.spec.bounds(i as Count);
+            if u == v {
+                continue;
+            }
+            let covered = choices
+                .iter()
+                .filter_map(|choice| choice.option)
+                .filter(|&k| {
+                    let opt = &self.spec.options()[k];
+                    opt.iter().any(|&(j, _)| j == i as Count)
+                })
+                .count();
+            let name = &self.items[i];
+            s.push_str(&format!("| {name} covered {covered} of {u}..{v}\n"));
+        }
+        s
+    }
+
+    // The items of option k, starting with the one at first.
+    fn format_items(&self, k: usize, first: usize) -> String {
+        let opt = &self.spec.options()[k];
+        let (before, after) = opt.split_at(first);
+        let names: Vec<String> = after
+            .iter()
+            .chain(before)
+            .map(|&(i, c)| match c {
+                0 => self.items[i as usize].clone(),
+                c => format!("{}:{}", self.items[i as usize], self.color(c)),
+            })
+            .collect();
+        names.join(" ")
+    }
+}
+
+// The number of a color, from 1, adding it if it is new.
+fn intern(colors: &mut Vec<String>, color: &str) -> usize {
+    match colors.iter().position(|c| c == color) {
+        Some(c) => c + 1,
+        None => {
+            colors.push(color.to_string());
+            colors.len()
+        }
+    }
+}
+
+// "u:v" or "v".
+fn parse_bounds(s: &str) -> Option<(Data, Data)> {
+    let (u, v) = match s.split_once(':') {
+        Some((u, v)) => (u.parse().ok()?, v.parse().ok()?),
+        None => {
+            let v = s.parse().ok()?;
+            (v, v)
+        }
+    };
+    (0 <= u && u <= v && 0 < v).then_some((u, v))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Problem;
+    use crate::choose::{self, MRVChooser};
+    use crate::spec::taocp_xcc;
+
+    #[test]
+    // TAocp Vol. 4B p. 89
+    fn test_parse() {
+        let dlx = Dlx::parse(
+            "| A simple example of color controls
+             p q r | x y
+             p q x y:A
+             p r x:A y
+             p x:B
+             q x:A
+             r y:B",
+        )
+        .unwrap();
+        let spec = taocp_xcc();
+        assert_eq!(dlx.spec(), &spec);
+        assert_eq!(dlx.items(), ["p", "q", "r", "x", "y"]);
+        assert_eq!(dlx.primary(), 3);
+        assert_eq!((dlx.color(1), dlx.color(2)), ("A", "B"));
+        assert_eq!(dlx.option(1), "p r x:A y");
+
+        let dlx = Dlx::parse("2|a 1:3|b c\na b\nb c").unwrap();
+        assert_eq!(dlx.spec().bounds(0), (2, 2));
+        assert_eq!(dlx.spec().bounds(1), (1, 3));
+        assert_eq!(dlx.spec().bounds(2), (1, 1));
+        assert_eq!(dlx.spec().secondary(), 0);
+    }
+
+    #[test]
+    fn test_format() {
+        let input = "a 0:2|b 2|c | x y
+                     a   x:red y:blue
+                     b  c
+                     c   y
+                     c b";
+        let dlx = Dlx::parse(input).unwrap();
+        assert_eq!(dlx.format_option(0), "a x:red y:blue");
+        assert_eq!(dlx.format_option(1), "b c");
+        assert_eq!(dlx.format_option(3), "c b");
+        let choice = Choice {
+            item: 2,
+            option: Some(3),
+            node: 0,
+            place: None,
+            len: 2,
+        };
+        assert_eq!(dlx.format_choice(&choice), "c b (?)");
+        let choice = Choice { node: 1, place: Some(1), ..choice };
+        assert_eq!(dlx.format_choice(&choice), "b c (1 of 2)");
+        let choice = Choice { item: 1, option: None, place: None, ..choice };
+        assert_eq!(dlx.format_choice(&choice), "null b");
+
+        // Each option starts with the item branched on, and c is chosen
+        // twice, the second time from what is left after tweaking.
+        let solutions = |input| {
+            let dlx = Dlx::parse(input).unwrap();
+            let spec = dlx.spec();
+            let mut problem = Problem::new(spec.items_m(), spec.onodes_c());
+            let mut chooser =
+                MRVChooser::new(choose::no_preference(), choose::first_wins());
+            let mut solutions = Vec::new();
+            while problem.next_solution(&mut chooser) {
+                let choices = problem.find_choices();
+                solutions.push(dlx.format_solution(&choices));
+            }
+            solutions
+        };
+        assert_eq!(
+            solutions(input),
+            ["a x:red y:blue (1 of 1)\nc b (1 of 2)\nc b (1 of 1)\n\
+                 | b covered 2 of 0..2\n"]
+        );
+        assert_eq!(
+            solutions("r p q\np q r\nq\nr p"),
+            ["r p q (1 of 2)\n", "r p (2 of 2)\nq (1 of 1)\n"]
+        );
+        // Item a is left uncovered by a null choice in the second solution,
+        // which has no place.
+        assert_eq!(
+            solutions("b 0:1|a\na b\nb"),
+            [
+                "b a (1 of 2)\n| a covered 1 of 0..1\n",
+                "b (2 of 2)\nnull a\n| a covered 0 of 0..1\n"
+            ]
+        );
+        // TAocp Vol. 4B p. 89
+        assert_eq!(
+            solutions("p q r | x y\np q x y:A\np r x:A y\np x:B\nq x:A\nr y:B"),
+            ["q x:A (2 of 2)\np r x:A y (1 of 1)\n"]
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        for (input, line, message) in [
+            ("", 0, "no items"),
+            ("| x\n\n", 0, "no items"),
+            ("a | b | c", 1, "'|' appears twice"),
+            ("a a", 1, "item \"a\" appears twice"),
+            ("a | 2|b", 1, "secondary item \"b\" has bounds"),
+            ("2:1|a", 1, "bad multiplicity \"2:1|a\""),
+            ("a:b", 1, "bad item name \"a:b\""),
+            ("a\n\n| comment\na b", 4, "unknown item \"b\""),
+            ("a b\na a", 2, "item \"a\" repeated"),
+            ("a | b\na:x b", 2, "primary item \"a\" has a color"),
+            ("a | b\na b:", 2, "empty color in \"b:\""),
+        ] {
+            let err = Dlx::parse(input).unwrap_err();
+            assert_eq!(err, ParseError { line, message: message.to_string() });
+        }
+        let err = Dlx::parse("a\nb").unwrap_err();
+        assert_eq!(err.to_string(), "line 2: unknown item \"b\"");
+    }
+}
@@ -0,0 +1,161 @@
+#![allow(clippy::unnecessary_cast)]
+use std::collections::HashMap;
+
+use crate::Count;
+use crate::links::{INodes, ONodes};
+use crate::polyomino::{Cell, rectangle};
+use crate::spec::Spec;
+
+// Every domino on two adjacent cells of the region, the cells in region
+// order. Option k of a tiling or Dominosa problem places domino k.
+pub fn dominoes(region: &[Cell]) -> Vec<(usize, usize)> {
+    let index: HashMap<Cell, usize> =
+        region.iter().enumerate().map(|(i, &c)| (c, i)).collect();
+    let mut dominoes = Vec::new();
+    for (i, &(x, y)) in region.iter().enumerate() {
+        for next in [(x + 1, y), (x, y + 1)] {
+            if let Some(&j) = index.get(&next) {
+                dominoes.push((i.min(j), i.max(j)));
+            }
+        }
+    }
+    dominoes
+}
+
+// Tilings of the region by dominoes; its cells are the primary items.
+pub fn tiling(region: &[Cell]) -> Spec<Count> {
+    let mut spec = Spec::new(region.len() as Count, 0);
+    for (i, j) in dominoes(region) {
+        spec.add_option([i as Count, j as Count]);
+    }
+    spec
+}
+
+pub fn problem(region: &[Cell]) -> (INodes, ONodes) {
+    let spec = tiling(region);
+    (spec.items(), spec.onodes())
+}
+
+// The cells whose centers lie within the diamond |x| + |y| <= n, which
+// has 2^(n(n+1)/2) domino tilings.
+pub fn aztec_diamond(n: i32) -> Vec<Cell> {
+    let mut cells = Vec::new();
+    for y in -n..n {
+        for x in -n..n {
+            if (2 * x + 1).abs() + (2 * y + 1).abs() <= 2 * n {
+                cells.push((x, y));
+            }
+        }
+    }
+    cells
+}
+
+// A Dominosa grid of n + 2 columns and n + 1 rows holds, row by row, the
+// numbers of a full set of dominoes from 0-0 to n-n. The dominoes must be
+// found again, each used once.
+pub fn dominosa(n: usize, grid: &[usize]) -> Spec<Count> {
+    let region = rectangle((n + 2) as i32, (n + 1) as i32);
+    assert!(grid.len() == region.len(), "wrong grid size");
+    assert!(grid.iter().all(|&v| v <= n), "number out of range");
+    // Dominoes a-b with a <= b, after the cells.
+    let pair = |a: usize, b: usize| {
+        let (a, b) = (a.min(b), a.max(b));
+        grid.len() + a * (2 * n + 3 - a) / 2 + (b - a)
+    };
+    let pairs = (n + 1) * (n + 2) / 2;
+    let mut spec = Spec::new((grid.len() + pairs) as Count, 0);
+    for (i, j) in dominoes(&region) {
+        let p = pair(grid[i], grid[j]);
+        spec.add_option([i as Count, j as Count, p as Count]);
+    }
+    spec
+}
+
+pub fn dominosa_problem(n: usize, grid: &[usize]) -> (INodes, ONodes) {
+    let spec = dominosa(n, grid);
+    (spec.items(), spec.onodes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Problem;
+    use crate::choose::{self, MRVChooser};
+
+    fn count(region: &[Cell]) -> u64 {
+        let (items, opts) = problem(region);
+        let mut chooser =
+            MRVChooser::new(choose::no_preference(), choose::first_wins());
+        Problem::new(items, opts).count_solutions(&mut chooser)
+    }
+
+    #[test]
+    fn test_rectangles() {
+        // 2 x n rectangles are counted by the Fibonacci numbers.
+        let (mut a, mut b) = (1, 1);
+        for n in 1..=12 {
+            assert_eq!(count(&rectangle(n, 2)), b, "2 x {n}");
+            (a, b) = (b, a + b);
+        }
+        for (w, h, expected) in [(3, 3, 0), (4, 3, 11), (4, 4, 36), (8, 3, 153)]
+        {
+            assert_eq!(count(&rectangle(w, h)), expected, "{w} x {h}");
+        }
+        assert_eq!(count(&rectangle(6, 6)), 6728);
+    }
+
+    #[test]
+    fn test_aztec_diamond() {
+        for n in 1..=5 {
+            let diamond = aztec_diamond(n);
+            assert_eq!(diamond.len() as i32, 2 * n * (n + 1));
+            assert_eq!(count(&diamond), 1 << (n * (n + 1) / 2), "order {n}");
+        }
+    }
+
+    #[test]
+    fn test_dominosa() {
+        // Lays the dominoes 0-0, 0-1, ..., n-n on the first tiling of the
+        // grid found, then checks that it can be found again.
+        let n = 3;
+        let region = rectangle(n as i32 + 2, n as i32 + 1);
+        let all = dominoes(&region);
+        let (items, opts) = problem(&region);
+        let mut chooser =
+            MRVChooser::new(choose::no_preference(), choose::first_wins());
+        let mut tiling = Problem::new(items, opts);
+        assert!(tiling.next_solution(&mut chooser));
+        tiling.find_options();
+        let mut laid: Vec<usize> =
+            tiling.get_options().iter().map(|&k| k as usize).collect();
+        laid.sort();
+        let mut grid = vec![0; region.len()];
+        let pairs = (0..=n).flat_map(|a| (a..=n).map(move |b| (a, b)));
+        for (&k, (a, b)) in laid.iter().zip(pairs) {
+            grid[all[k].0] = a;
+            grid[all[k].1] = b;
+        }
+
+        let (items, opts) = dominosa_problem(n, &grid);
+        let mut problem = Problem::new(items, opts);
+        let mut found = Vec::new();
+        while problem.next_solution(&mut chooser) {
+            problem.find_options();
+            let mut options: Vec<usize> =
+                problem.get_options().iter().map(|&k| k as usize).collect();
+            options.sort();
+            let mut used: Vec<(usize, usize)> = options
+                .iter()
+                .map(|&k| {
+                    let (a, b) = (grid[all[k].0], grid[all[k].1]);
+                    (a.min(b), a.max(b))
+                })
+                .collect();
+            used.sort();
+            used.dedup();
+            assert_eq!(used.len(), (n + 1) * (n + 2) / 2);
+            found.push(options);
+        }
+        assert!(found.contains(&laid));
+    }
+}
@@ -0,0 +1,105 @@
+#![allow(clippy::unnecessary_cast)]
+use std::fmt::Debug;
+
+use crate::{Count, Data, Link};
+
+// An integer type the nodes store links in, with a signed type of the same
+// width for the data stored alongside them (OLEN, TOP, colors and bounds).
+// Narrower types make the nodes smaller; the search itself works with Link
+// and Data. The conversions do not check: node types check once, when they
+// are made, that every value they will hold fits.
+pub trait Index: Copy + Debug + Default + Eq {
+    type Signed: Copy + Debug + Default + Eq;
+
+    // The largest link or count that fits, as the signed type too.
+    const MAX: Link;
+
+    fn from_link(x: Link) -> Self;
+    fn link(self) -> Link;
+    fn from_data(x: Data) -> Self::Signed;
+    fn data(x: Self::Signed) -> Data;
+
+    fn fits(x: Data) -> bool {
+        x.unsigned_abs() <= Self::MAX
+    }
+}
+
+macro_rules! index {
+    ($u:ty, $s:ty) => {
+        const _: () = {
+            assert!(size_of::<$u>() == size_of::<$s>());
+            assert!(<$s>::MAX as u128 <= <$u>::MAX as u128);
+            assert!(<$u>::MAX as u128 <= Link::MAX as u128);
+            assert!(<$s>::MAX as u128 <= Data::MAX as u128);
+        };
+
+        impl Index for $u {
+            type Signed = $s;
+
+            const MAX: Link = <$s>::MAX as Link;
+
+            #[inline(always)]
+            fn from_link(x: Link) -> $u {
+                x as $u
+            }
+
+            #[inline(always)]
+            fn link(self) -> Link {
+                self as Link
+            }
+
+            #[inline(always)]
+            fn from_data(x: Data) -> $s {
+                x as $s
+            }
+
+            #[inline(always)]
+            fn data(x: $s) -> Data {
+                x as Data
+            }
+        }
+    };
+}
+
+index!(u32, i32);
+#[cfg(target_pointer_width = "64")]
+index!(u64, i64);
+index!(usize, isize);
+
+// The length of a node vector with item headers for n items, and m options
+// with l nodes in all: a header, the n items, and m + 1 spacers around the
+// option nodes. Panics unless every link into it fits in L.
+pub fn node_count<L: Index>(n: Count, m: Count, l: Count) -> Count {
+    let len = [m, n, 2].into_iter().try_fold(l, Count::checked_add);
+    let len = len.expect("too many nodes");
+    let name = std::any::type_name::<L>();
+    assert!(len - 1 <= L::MAX, "too many nodes for {name}");
+    len
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_index() {
+        assert_eq!(<u32 as Index>::MAX, i32::MAX as Link);
+        assert_eq!(u32::from_link(7).link(), 7);
+        assert_eq!(u32::data(u32::from_data(-5)), -5);
+        assert!(u32::fits(-(i32::MAX as Data)) && !u32::fits(1 << 31));
+        assert_eq!(node_count::<u32>(3, 5, 14), 24);
+        assert_eq!(node_count::<u32>(0, 0, i32::MAX as Count - 1), 1 << 31);
+    }
+
+    #[test]
+    #[should_panic(expected = "too many nodes for u32")]
+    fn test_too_many() {
+        node_count::<u32>(0, 0, i32::MAX as Count);
+    }
+
+    #[test]
+    #[should_panic(expected = "too many nodes")]
+    fn test_overflow() {
+        node_count::<usize>(1, 1, Count::MAX - 2);
+    }
+}
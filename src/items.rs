@@ -4,8 +4,10 @@ pub trait Items {
     fn primary(&self) -> Count;
     fn secondary(&self) -> Count;
 
-    fn llink(&mut self, i: Link) -> &mut Link;
-    fn rlink(&mut self, i: Link) -> &mut Link;
+    fn llink(&mut self, i: Link) -> Link;
+    fn set_llink(&mut self, i: Link, x: Link);
+    fn rlink(&mut self, i: Link) -> Link;
+    fn set_rlink(&mut self, i: Link, x: Link);
 
     fn bound(&mut self, i: Link) -> Data;
     fn dec_bound(&mut self, i: Link) -> Data;
@@ -17,14 +19,14 @@ pub trait Items {
         let n1 = self.primary();
         let n = self.primary() + self.secondary();
         for i in (1 as Link)..=n {
-            *self.llink(i) = i - 1;
-            *self.rlink(i - 1) = i;
+            self.set_llink(i, i - 1);
+            self.set_rlink(i - 1, i);
         }
-        *self.llink(n + 1) = n;
-        *self.rlink(n) = n + 1;
-        *self.llink(n1 + 1) = n + 1;
-        *self.rlink(n + 1) = n1 + 1;
-        *self.llink(0) = n1;
-        *self.rlink(n1) = 0;
+        self.set_llink(n + 1, n);
+        self.set_rlink(n, n + 1);
+        self.set_llink(n1 + 1, n + 1);
+        self.set_rlink(n + 1, n1 + 1);
+        self.set_llink(0, n1);
+        self.set_rlink(n1, 0);
     }
 }
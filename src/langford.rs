@@ -0,0 +1,142 @@
+#![allow(clippy::unnecessary_cast)]
+use crate::Count;
+use crate::spec::Spec;
+
+// Sequences of length 2n holding two copies of each k in 1..=n, the
+// second copy of k lying k + offset places after the first: Langford pairs
+// L(2, n) have offset 1, Skolem sequences offset 0.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Pairs {
+    n: usize,
+    offset: usize,
+    reduce: bool,
+}
+
+impl Pairs {
+    pub fn langford(n: usize) -> Pairs {
+        assert!(n > 0);
+        Pairs { n, offset: 1, reduce: false }
+    }
+
+    pub fn skolem(n: usize) -> Pairs {
+        assert!(n > 0);
+        Pairs { n, offset: 0, reduce: false }
+    }
+
+    // Every sequence reversed is another solution. With reduction on, one
+    // value whose pair can never be its own mirror image is only placed in
+    // the left half, so just one of each such couple is found.
+    pub fn set_symmetry_reduction(&mut self, reduce: bool) {
+        self.reduce = reduce;
+    }
+
+    // Items are the values 1..=n, then the positions 1..=2n.
+    pub fn spec(&self) -> Spec<Count> {
+        let n = self.n;
+        let mut spec = Spec::new((3 * n) as Count, 0);
+        for (k, i) in self.placements() {
+            let d = k + self.offset;
+            spec.add_option([
+                (k - 1) as Count,
+                (n + i) as Count,
+                (n + i + d) as Count,
+            ]);
+        }
+        spec
+    }
+
+    pub fn sequence(&self, options: &[isize]) -> Vec<usize> {
+        let placements = self.placements();
+        let mut seq = vec![0; 2 * self.n];
+        for &o in options {
+            let (k, i) = placements[o as usize];
+            seq[i] = k;
+            seq[i + k + self.offset] = k;
+        }
+        seq
+    }
+
+    // The value and first position made by each option.
+    fn placements(&self) -> Vec<(usize, usize)> {
+        let len = 2 * self.n;
+        // The pair (i, i + d) mirrors to (len - 1 - d - i, len - 1 - i),
+        // which is itself only when d is odd.
+        let fixed = (1..=self.n)
+            .rev()
+            .find(|&k| (k + self.offset).is_multiple_of(2))
+            .filter(|_| self.reduce);
+        let mut placements = Vec::new();
+        for k in 1..=self.n {
+            let d = k + self.offset;
+            for i in 0..len.saturating_sub(d) {
+                if Some(k) == fixed && 2 * i >= len - 1 - d {
+                    continue;
+                }
+                placements.push((k, i));
+            }
+        }
+        placements
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Problem;
+    use crate::choose::{self, MRVChooser};
+
+    fn sequences(pairs: &Pairs) -> Vec<Vec<usize>> {
+        let spec = pairs.spec();
+        let mut problem = Problem::new(spec.items(), spec.onodes());
+        let mut chooser =
+            MRVChooser::new(choose::no_preference(), choose::first_wins());
+        let mut seqs = Vec::new();
+        while problem.next_solution(&mut chooser) {
+            problem.find_options();
+            seqs.push(pairs.sequence(problem.get_options()));
+        }
+        seqs.sort();
+        seqs
+    }
+
+    #[test]
+    fn test_langford() {
+        let mut pairs = Pairs::langford(3);
+        assert_eq!(sequences(&pairs), [[2, 3, 1, 2, 1, 3], [3, 1, 2, 1, 3, 2]]);
+        pairs.set_symmetry_reduction(true);
+        assert_eq!(sequences(&pairs), [[3, 1, 2, 1, 3, 2]]);
+
+        for (n, expected) in [(4, 1), (5, 0), (7, 26), (8, 150)] {
+            let mut pairs = Pairs::langford(n);
+            let all = sequences(&pairs);
+            assert_eq!(all.len(), 2 * expected, "L(2, {n})");
+            pairs.set_symmetry_reduction(true);
+            let reduced = sequences(&pairs);
+            assert_eq!(reduced.len(), expected, "L(2, {n}) up to reversal");
+            for seq in reduced {
+                let mut rev = seq.clone();
+                rev.reverse();
+                assert!(all.binary_search(&seq).is_ok());
+                assert!(all.binary_search(&rev).is_ok());
+            }
+        }
+    }
+
+    #[test]
+    fn test_skolem() {
+        assert_eq!(sequences(&Pairs::skolem(1)), [[1, 1]]);
+        for (n, expected) in [(3, 0), (4, 3), (5, 5), (8, 252)] {
+            let mut pairs = Pairs::skolem(n);
+            assert_eq!(sequences(&pairs).len(), 2 * expected, "S({n})");
+            pairs.set_symmetry_reduction(true);
+            let reduced = sequences(&pairs);
+            assert_eq!(reduced.len(), expected, "S({n}) up to reversal");
+            assert!(reduced.iter().all(|s| {
+                (1..=n).all(|k| {
+                    let i = s.iter().position(|&v| v == k).unwrap();
+                    s[i + k] == k
+                })
+            }));
+        }
+    }
+}
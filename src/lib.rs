@@ -1,10 +1,34 @@
+pub mod arrays;
+pub mod bits;
+pub mod cages;
+pub mod check;
 pub mod choose;
+pub mod coloring;
+pub mod cover;
+pub mod crossword;
+pub mod dlx;
+pub mod dominoes;
+pub mod index;
 pub mod items;
+pub mod langford;
 pub mod links;
+pub mod partition;
+pub mod polyomino;
+pub mod queens;
+pub mod random;
+pub mod reference;
+pub mod restart;
+pub mod sample;
+pub mod schedule;
+pub mod spec;
+pub mod sudoku;
+pub mod verify;
 
 use choose::Choose;
 use items::Items;
 
+// The types links, counts and data are handled in. The nodes may store
+// them in narrower types; see index::Index.
 pub type Link = usize;
 pub type Count = Link;
 pub type Data = isize;
@@ -15,8 +39,23 @@ const _: () = {
     assert!(Data::MAX as u128 <= u64::MAX as u128);
     assert!(Data::MAX as u128 <= Count::MAX as u128);
     assert!(Data::MAX as u128 <= Link::MAX as u128);
+    assert!(size_of::<Data>() == size_of::<Link>());
 };
 
+// One level of the current solution, as Knuth's DLX programs report it:
+// the item branched on, from 0, and the option chosen for it, if any,
+// with the place in that option of the item's node. The option was number
+// place of the len the item had when chosen; there is no place without an
+// option, or if it was not found.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Choice {
+    pub item: Count,
+    pub option: Option<usize>,
+    pub node: usize,
+    pub place: Option<Count>,
+    pub len: Data,
+}
+
 pub struct Problem<I, O>
 where
     I: Items,
@@ -26,11 +65,18 @@ where
     opts: O,
     x: Vec<Link>,
     ft: Vec<Link>,
+    lens: Vec<Data>,
     o: Vec<isize>,
     l: Link,
     i: Link,
     updates: isize,
     restart: bool,
+    profile: Vec<u64>,
+    nodes: u64,
+    limit: Option<u64>,
+    paused: bool,
+    failures: Vec<Link>,
+    colors: Vec<Data>,
 }
 
 impl<I, O> Problem<I, O>
@@ -44,11 +90,18 @@ where
             opts,
             x: Vec::new(),
             ft: Vec::new(),
+            lens: Vec::new(),
             o: Vec::new(),
             l: 0,
             i: 0,
             updates: 0,
             restart: false,
+            profile: Vec::new(),
+            nodes: 0,
+            limit: None,
+            paused: false,
+            failures: Vec::new(),
+            colors: Vec::new(),
         }
     }
 
@@ -58,7 +111,11 @@ where
         let mut i = self.i;
         if self.updates < 0 {
             self.updates = 0;
+            self.profile.clear();
+            self.nodes = 0;
+            self.failures.clear();
         }
+        self.paused = false;
 
         let n = self.items.primary() + self.items.secondary();
         let n1 = self.items.primary();
@@ -69,7 +126,23 @@ where
             // goto M9
             } else {
                 // M2
-                if *self.items.rlink(0) == 0 {
+                #[cfg(feature = "check-invariants")]
+                if let Err(err) = self.check_invariants() {
+                    panic!("at level {l}: {err}");
+                }
+                if self.limit.is_some_and(|limit| self.nodes >= limit) {
+                    self.l = l;
+                    self.i = i;
+                    self.limit = None;
+                    self.paused = true;
+                    return false;
+                }
+                if self.profile.len() == l as usize {
+                    self.profile.push(0);
+                }
+                self.profile[l as usize] += 1;
+                self.nodes += 1;
+                if self.items.rlink(0) == 0 {
                     self.l = l;
                     self.i = i;
                     self.restart = true;
@@ -78,14 +151,22 @@ where
                     if self.x.len() == l as usize {
                         self.x.push(0);
                         self.ft.push(0);
+                        self.lens.push(0);
                     }
                     // M3
+                    if C::WANTS_FAILURES {
+                        for f in self.failures.drain(..) {
+                            chooser.failed(f);
+                        }
+                    }
                     i = chooser.choose(&mut self.items, &mut self.opts);
-                    if (1 + *self.opts.olen(i)) > self.items.branch_factor(i) {
+                    if (1 + self.opts.olen(i)) > self.items.branch_factor(i) {
                         // M4
-                        self.x[l as usize] = *self.opts.dlink(i);
+                        self.x[l as usize] = self.opts.dlink(i);
+                        self.lens[l as usize] = self.opts.olen(i);
+                        self.ft[l as usize] = 0;
                         if self.items.dec_bound(i) == 0 {
-                            self.cover(i);
+                            self.cover::<C>(i);
                             if self.items.slack(i) != 0 {
                                 self.ft[l as usize] = self.x[l as usize];
                             }
@@ -94,7 +175,7 @@ where
                         }
 
                         // M5,M6
-                        if self.try_item(i, self.x[l as usize], n1) {
+                        if self.try_item::<C>(i, self.x[l as usize], n1) {
                             l += 1;
                             continue;
                             // go to M2
@@ -115,26 +196,12 @@ where
                 }
                 l -= 1;
                 if self.x[l as usize] > n {
-                    i = *self.opts.top(self.x[l as usize]) as Link;
+                    i = self.opts.top(self.x[l as usize]) as Link;
                     // M7
-                    let mut p = self.x[l as usize] - 1;
-                    while p != self.x[l as usize] {
-                        let j = *self.opts.top(p);
-                        if j <= 0 {
-                            p = *self.opts.dlink(p);
-                        } else if j as Link <= n1 {
-                            p -= 1;
-                            if self.items.inc_bound(j as Link) == 1 {
-                                self.uncover(j as Link);
-                            }
-                        } else {
-                            self.uncommit(p, j as Link);
-                            p -= 1;
-                        }
-                    }
-                    self.x[l as usize] = *self.opts.dlink(self.x[l as usize]);
+                    self.undo_option(self.x[l as usize], n1);
+                    self.x[l as usize] = self.opts.dlink(self.x[l as usize]);
                     // M5,M6
-                    if self.try_item(i, self.x[l as usize], n1) {
+                    if self.try_item::<C>(i, self.x[l as usize], n1) {
                         l += 1;
                         break;
                         // next: M2
@@ -142,18 +209,32 @@ where
                     // next: M8
                 } else {
                     i = self.x[l as usize];
-                    let p = *self.items.llink(i);
-                    let q = *self.items.rlink(i);
-                    *self.items.rlink(p) = i;
-                    *self.items.llink(q) = i;
+                    let p = self.items.llink(i);
+                    let q = self.items.rlink(i);
+                    self.items.set_rlink(p, i);
+                    self.items.set_llink(q, i);
                     // next: M8
                 }
                 // M8
                 self.restore_item(i, self.ft[l as usize], n);
+                #[cfg(feature = "check-invariants")]
+                if let Err(err) = self.check_invariants() {
+                    panic!("backtracking to level {l}: {err}");
+                }
             }
         }
     }
 
+    // Runs the search to the end, leaving the problem as it was when the
+    // search began.
+    pub fn count_solutions<C: Choose<I>>(&mut self, chooser: &mut C) -> u64 {
+        let mut count = 0;
+        while self.next_solution(chooser) {
+            count += 1;
+        }
+        count
+    }
+
     #[allow(clippy::unnecessary_cast)]
     pub fn find_options(&mut self) {
         let n = self.items.primary() + self.items.secondary();
@@ -164,19 +245,121 @@ where
                 // TODO: somehow report this
                 continue;
             }
-            while *self.opts.top(r) >= 0 {
+            while self.opts.top(r) >= 0 {
                 r += 1;
             }
             // Internal option indexes are 1-based
-            self.o.push(-*self.opts.top(r) - 1);
+            self.o.push((-self.opts.top(r) - 1) as isize);
+        }
+    }
+
+    pub fn get_options(&self) -> &[isize] {
+        &self.o
+    }
+
+    // The choices that led to the current solution, level by level.
+    #[allow(clippy::unnecessary_cast)]
+    pub fn find_choices(&mut self) -> Vec<Choice> {
+        let n = self.items.primary() + self.items.secondary();
+        let mut choices = Vec::new();
+        for l in 0..self.l as usize {
+            let x = self.x[l];
+            let (i, option, node) = if x <= n {
+                (x, None, 0)
+            } else {
+                let mut r = x;
+                while self.opts.top(r) > 0 {
+                    r += 1;
+                }
+                let first = self.opts.ulink(r);
+                let k = (-self.opts.top(r) - 1) as usize;
+                (self.opts.top(x) as Link, Some(k), (x - first) as usize)
+            };
+            // Options passed over at this level were tweaked out of the
+            // list, but still link to the rest from the first of them.
+            let mut place = option.map(|_| 1);
+            let mut q = match self.ft[l] {
+                0 => self.opts.dlink(i),
+                ft => ft,
+            };
+            while q != x && place.is_some() {
+                place = if q == i { None } else { place.map(|k| k + 1) };
+                q = self.opts.dlink(q);
+            }
+            let len = self.lens[l];
+            choices.push(Choice { item: i - 1, option, node, place, len });
         }
+        choices
     }
 
     pub fn get_updates(&self) -> isize {
         self.updates.abs()
     }
 
-    fn try_item(&mut self, i: Link, xl: Link, n1: Count) -> bool {
+    // The number of nodes of the search tree visited at each level.
+    pub fn get_profile(&self) -> &[u64] {
+        &self.profile
+    }
+
+    pub fn nodes(&self) -> u64 {
+        self.nodes
+    }
+
+    // Once the search has visited this many nodes in all, next_solution
+    // returns false and is_paused returns true; calling next_solution again
+    // carries on from there. The limit applies only once.
+    pub fn set_node_limit(&mut self, limit: u64) {
+        self.limit = Some(limit);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    // See check::check. With the check-invariants feature, next_solution
+    // calls this at every step and panics if it fails. The colors the nodes
+    // are checked against are taken the first time it is called.
+    pub fn check_invariants(&mut self) -> Result<(), String> {
+        if self.colors.is_empty() {
+            self.colors = check::colors(&mut self.items, &mut self.opts);
+        }
+        check::check(&mut self.items, &mut self.opts, &self.colors)
+    }
+
+    // Backs out of the search, wherever it stopped, leaving the items and
+    // options as they were before it began; the next call to next_solution
+    // starts a new search, perhaps with another chooser. The statistics
+    // start again from zero, and any node limit is dropped.
+    #[allow(clippy::unnecessary_cast)]
+    pub fn reset(&mut self) {
+        let n = self.items.primary() + self.items.secondary();
+        let n1 = self.items.primary();
+        while self.l > 0 {
+            self.l -= 1;
+            let l = self.l as usize;
+            let i = if self.x[l] > n {
+                self.undo_option(self.x[l], n1);
+                self.opts.top(self.x[l]) as Link
+            } else {
+                let i = self.x[l];
+                let p = self.items.llink(i);
+                let q = self.items.rlink(i);
+                self.items.set_rlink(p, i);
+                self.items.set_llink(q, i);
+                i
+            };
+            self.restore_item(i, self.ft[l], n);
+        }
+        self.restart = false;
+        self.updates = 0;
+        self.profile.clear();
+        self.nodes = 0;
+        self.limit = None;
+        self.paused = false;
+        self.failures.clear();
+    }
+
+    fn try_item<C: Choose<I>>(&mut self, i: Link, xl: Link, n1: Count) -> bool {
         // M5
         if self.items.slack(i) == 0 && self.items.bound(i) == 0 {
             if xl == i {
@@ -184,33 +367,33 @@ where
                 // go to M8
             }
             // go to M6
-        } else if *self.opts.olen(i)
+        } else if self.opts.olen(i)
             <= (self.items.bound(i) - self.items.slack(i))
         {
             return false;
             // go to M8
         } else if xl != i {
-            self.tweak(xl, i);
+            self.tweak::<C>(xl, i);
         } else if self.items.bound(i) != 0 {
-            let p = *self.items.llink(i);
-            let q = *self.items.rlink(i);
-            *self.items.rlink(p) = q;
-            *self.items.llink(q) = p;
+            let p = self.items.llink(i);
+            let q = self.items.rlink(i);
+            self.items.set_rlink(p, q);
+            self.items.set_llink(q, p);
         }
         // M6
         if xl != i {
             let mut p = xl + 1;
             while p != xl {
-                let j = *self.opts.top(p);
+                let j = self.opts.top(p);
                 if j <= 0 {
-                    p = *self.opts.ulink(p);
+                    p = self.opts.ulink(p);
                 } else if j as Count <= n1 {
                     p += 1;
                     if self.items.dec_bound(j as Link) == 0 {
-                        self.cover(j as Link);
+                        self.cover::<C>(j as Link);
                     }
                 } else {
-                    self.commit(p, j as Link);
+                    self.commit::<C>(p, j as Link);
                     p += 1;
                 }
             }
@@ -218,6 +401,26 @@ where
         true
     }
 
+    // M7, for the option with node xl: uncovers its other items in the
+    // reverse of the order in which M6 covered them.
+    fn undo_option(&mut self, xl: Link, n1: Count) {
+        let mut p = xl - 1;
+        while p != xl {
+            let j = self.opts.top(p);
+            if j <= 0 {
+                p = self.opts.dlink(p);
+            } else if j as Link <= n1 {
+                p -= 1;
+                if self.items.inc_bound(j as Link) == 1 {
+                    self.uncover(j as Link);
+                }
+            } else {
+                self.uncommit(p, j as Link);
+                p -= 1;
+            }
+        }
+    }
+
     fn restore_item(&mut self, i: Link, ftl: Link, n: Count) {
         if self.items.bound(i) == 0 && self.items.slack(i) == 0 {
             self.uncover(i);
@@ -228,12 +431,12 @@ where
         self.items.inc_bound(i);
     }
 
-    fn commit(&mut self, p: Link, j: Link) {
+    fn commit<C: Choose<I>>(&mut self, p: Link, j: Link) {
         if self.opts.get_color(p) == 0 {
-            self.cover(j);
+            self.cover::<C>(j);
         }
         if self.opts.get_color(p) > 0 {
-            self.purify(p);
+            self.purify::<C>(p);
         }
     }
 
@@ -246,132 +449,150 @@ where
         }
     }
 
-    fn cover(&mut self, i: Link) {
+    fn cover<C: Choose<I>>(&mut self, i: Link) {
         self.updates += 1;
-        let mut p = *self.opts.dlink(i);
+        let mut p = self.opts.dlink(i);
         while p != i {
-            self.hide(p);
-            p = *self.opts.dlink(p);
+            self.hide::<C>(p);
+            p = self.opts.dlink(p);
         }
-        let l = *self.items.llink(i);
-        let r = *self.items.rlink(i);
-        *self.items.rlink(l) = r;
-        *self.items.llink(r) = l;
+        let l = self.items.llink(i);
+        let r = self.items.rlink(i);
+        self.items.set_rlink(l, r);
+        self.items.set_llink(r, l);
     }
 
     fn uncover(&mut self, i: Link) {
-        let l = *self.items.llink(i);
-        let r = *self.items.rlink(i);
-        *self.items.rlink(l) = i;
-        *self.items.llink(r) = i;
-        let mut p = *self.opts.ulink(i);
+        let l = self.items.llink(i);
+        let r = self.items.rlink(i);
+        self.items.set_rlink(l, i);
+        self.items.set_llink(r, i);
+        let mut p = self.opts.ulink(i);
         while p != i {
             self.unhide(p);
-            p = *self.opts.ulink(p);
+            p = self.opts.ulink(p);
         }
     }
 
-    fn hide(&mut self, p: Link) {
+    // The chooser type only says whether to keep track of failures, so
+    // searches that do not want them pay nothing for them.
+    fn hide<C: Choose<I>>(&mut self, p: Link) {
         let mut q = p + 1;
         while q != p {
-            let x = *self.opts.top(q);
-            let u = *self.opts.ulink(q);
-            let d = *self.opts.dlink(q);
+            let x = self.opts.top(q);
+            let u = self.opts.ulink(q);
+            let d = self.opts.dlink(q);
             if x <= 0 {
                 q = u;
             } else {
                 if self.opts.get_color(q) >= 0 {
-                    *self.opts.dlink(u) = d;
-                    *self.opts.ulink(d) = u;
+                    self.opts.set_dlink(u, d);
+                    self.opts.set_ulink(d, u);
                     self.updates += 1;
-                    *self.opts.olen(x as Link) -= 1;
+                    let len = self.opts.olen(x as Link) - 1;
+                    self.opts.set_olen(x as Link, len);
+                    if C::WANTS_FAILURES && len == 0 {
+                        self.fail(x as Link);
+                    }
                 }
                 q += 1;
             }
         }
     }
 
+    // Primary items with slack have not failed when their lists run out.
+    fn fail(&mut self, i: Link) {
+        if i <= self.items.primary() && self.items.branch_factor(i) > 0 {
+            self.failures.push(i);
+        }
+    }
+
     fn unhide(&mut self, p: Link) {
         let mut q = p - 1;
         while q != p {
-            let x = *self.opts.top(q);
-            let u = *self.opts.ulink(q);
-            let d = *self.opts.dlink(q);
+            let x = self.opts.top(q);
+            let u = self.opts.ulink(q);
+            let d = self.opts.dlink(q);
             if x <= 0 {
                 q = d;
             } else {
                 if self.opts.get_color(q) >= 0 {
-                    *self.opts.dlink(u) = q;
-                    *self.opts.ulink(d) = q;
-                    *self.opts.olen(x as Link) += 1;
+                    self.opts.set_dlink(u, q);
+                    self.opts.set_ulink(d, q);
+                    let len = self.opts.olen(x as Link) + 1;
+                    self.opts.set_olen(x as Link, len);
                 }
                 q -= 1;
             }
         }
     }
 
-    fn purify(&mut self, p: Link) {
+    fn purify<C: Choose<I>>(&mut self, p: Link) {
         let c = self.opts.get_color(p);
-        let i = *self.opts.top(p) as Link;
-        self.opts.set_color(i, c); // HMM not needed?
-        let mut q = *self.opts.dlink(i);
+        let i = self.opts.top(p) as Link;
+        // Marks the item purified, for check::check.
+        self.opts.set_color(i, c);
+        let mut q = self.opts.dlink(i);
         while q != i {
             if self.opts.get_color(q) == c {
                 self.opts.set_color(q, -1);
             } else {
-                self.hide(q)
+                self.hide::<C>(q)
             }
-            q = *self.opts.dlink(q);
+            q = self.opts.dlink(q);
         }
     }
 
     fn unpurify(&mut self, p: Link) {
         let c = self.opts.get_color(p);
-        let i = *self.opts.top(p) as Link;
-        let mut q = *self.opts.ulink(i);
+        let i = self.opts.top(p) as Link;
+        let mut q = self.opts.ulink(i);
         while q != i {
             if self.opts.get_color(q) < 0 {
                 self.opts.set_color(q, c);
             } else {
                 self.unhide(q);
             }
-            q = *self.opts.ulink(q);
+            q = self.opts.ulink(q);
         }
+        self.opts.set_color(i, 0);
     }
 
-    fn tweak(&mut self, x: Link, p: Link) {
+    fn tweak<C: Choose<I>>(&mut self, x: Link, p: Link) {
         // "We will tweak(x, p) only when x = DLINK(p) and p = ULINK(x)."
         if self.items.bound(p) != 0 {
-            self.hide(x);
+            self.hide::<C>(x);
         }
-        let d = *self.opts.dlink(x);
-        *self.opts.dlink(p) = d;
-        *self.opts.ulink(d) = p;
-        *self.opts.olen(p) -= 1;
+        let d = self.opts.dlink(x);
+        self.opts.set_dlink(p, d);
+        self.opts.set_ulink(d, p);
+        let len = self.opts.olen(p) - 1;
+        self.opts.set_olen(p, len);
     }
 
     fn untweak(&mut self, ftl: Link, n: Count, unblock: bool) {
         let p = if ftl <= n {
             ftl
         } else {
-            *self.opts.top(ftl) as Link
+            self.opts.top(ftl) as Link
         };
         let mut x = ftl;
         let mut y = p;
-        let z = *self.opts.dlink(p);
-        *self.opts.dlink(p) = x;
+        let z = self.opts.dlink(p);
+        self.opts.set_dlink(p, x);
         let mut k = 0;
         while x != z {
-            *self.opts.ulink(x) = y;
+            self.opts.set_ulink(x, y);
             k += 1;
             if unblock {
                 self.unhide(x);
             }
             y = x;
-            x = *self.opts.dlink(x);
+            x = self.opts.dlink(x);
         }
-        *self.opts.ulink(z) = y;
-        *self.opts.olen(p) += k;
+        self.opts.set_ulink(z, y);
+        let len = self.opts.olen(p) + k;
+        self.opts.set_olen(p, len);
         if !unblock {
             self.uncover(p);
         }
@@ -386,15 +607,23 @@ pub trait OptSpec {
 pub trait ODance {
     type Spec: OptSpec;
 
-    fn olen(&mut self, i: Link) -> &mut Data;
-    fn top(&mut self, i: Link) -> &mut Data;
-    fn ulink(&mut self, i: Link) -> &mut Link;
-    fn dlink(&mut self, i: Link) -> &mut Link;
+    fn olen(&mut self, i: Link) -> Data;
+    fn set_olen(&mut self, i: Link, len: Data);
+    fn top(&mut self, i: Link) -> Data;
+    fn set_top(&mut self, i: Link, t: Data);
+    fn ulink(&mut self, i: Link) -> Link;
+    fn set_ulink(&mut self, i: Link, x: Link);
+    fn dlink(&mut self, i: Link) -> Link;
+    fn set_dlink(&mut self, i: Link, x: Link);
 
     fn get_color(&mut self, i: Link) -> Data;
     fn set_color(&mut self, i: Link, c: Data);
 
     fn size(&self) -> Count;
+    // The number of nodes: item headers, option nodes and spacers. There
+    // is no default, since it cannot be told from the links alone, so
+    // implementations from before check::check must add it.
+    fn nodes(&self) -> Count;
 
     // TODO: allow for randomization
     fn init_links(
@@ -405,10 +634,10 @@ pub trait ODance {
     ) {
         let n = np + ns;
         for i in (1 as Link)..=n {
-            *self.ulink(i) = i;
-            *self.dlink(i) = i;
+            self.set_ulink(i, i);
+            self.set_dlink(i, i);
         }
-        let mut m: isize = 0;
+        let mut m: Data = 0;
         let mut p: Link = n + 1;
         for opts in opt_spec.into_iter() {
             let mut k = 0;
@@ -417,21 +646,22 @@ pub trait ODance {
                 // Internal item numbers are 1-based.
                 let ij = opt.get_item() + 1;
                 k += 1;
-                *self.olen(ij) += 1;
-                let q = *self.ulink(ij);
-                *self.ulink(p + k) = q;
-                *self.dlink(q) = p + k;
-                *self.dlink(p + k) = ij;
-                *self.ulink(ij) = p + k;
-                *self.top(p + k) = ij as Data;
+                let len = self.olen(ij) + 1;
+                self.set_olen(ij, len);
+                let q = self.ulink(ij);
+                self.set_ulink(p + k, q);
+                self.set_dlink(q, p + k);
+                self.set_dlink(p + k, ij);
+                self.set_ulink(ij, p + k);
+                self.set_top(p + k, ij as Data);
                 let c = if ij > np { opt.get_color() } else { 0 };
                 self.set_color(p + k, c);
             }
             m += 1;
-            *self.dlink(p) = p + k;
+            self.set_dlink(p, p + k);
             p = p + k + 1;
-            *self.top(p) = -m;
-            *self.ulink(p) = p - k;
+            self.set_top(p, -m);
+            self.set_ulink(p, p - k);
         }
     }
 }
@@ -468,7 +698,7 @@ mod tests {
         assert_eq!(problem.items, items_init, "items not backtracked");
         assert_eq!(problem.opts, opts_init, "options not backtracked");
         assert!(
-            problem.l == 0 && problem.restart == false,
+            problem.l == 0 && !problem.restart,
             "initial state not restored"
         );
     }
@@ -493,13 +723,7 @@ mod tests {
     // TAocp Vol. 4B p. 89
     fn test_xcc() {
         let items = INode::make_nodes(3, 2);
-        let opt_spec: Vec<Vec<(Count, Data)>> = vec![
-            vec![(0, 0), (1, 0), (3, 0), (4, 1)],
-            vec![(0, 0), (2, 0), (3, 1), (4, 0)],
-            vec![(0, 0), (3, 2)],
-            vec![(1, 0), (3, 1)],
-            vec![(2, 0), (4, 2)],
-        ];
+        let opt_spec = spec::taocp_xcc().options().to_vec();
         let opts = ONodeC::make_nodes(3, 2, 5, 14, opt_spec);
         verify_solutions(items, opts, vec![vec![1, 3]]);
     }
@@ -536,4 +760,215 @@ mod tests {
             ],
         );
     }
+
+    #[test]
+    fn test_arrays() {
+        use super::arrays::*;
+        let items = INode::make_nodes(7, 0);
+        let opt_spec: Vec<Vec<Count>> = vec![
+            vec![2, 4],
+            vec![0, 3, 6],
+            vec![1, 2, 5],
+            vec![0, 3, 5],
+            vec![1, 6],
+            vec![3, 4, 6],
+        ];
+        let opts = OArrays::make_nodes(7, 0, 6, 16, opt_spec);
+        verify_solutions(items, opts, vec![vec![0, 3, 4]]);
+
+        let items = INode::make_nodes(3, 2);
+        let opt_spec = spec::taocp_xcc().options().to_vec();
+        let opts = OArraysC::make_nodes(3, 2, 5, 14, opt_spec);
+        verify_solutions(items, opts, vec![vec![1, 3]]);
+    }
+
+    #[test]
+    fn test_index_types() {
+        use super::arrays::*;
+        assert_eq!(size_of::<ONode<u32>>(), 12);
+        assert_eq!(size_of::<ONodeC<u32>>(), 16);
+
+        let opt_spec = spec::taocp_xcc().options().to_vec();
+        let items = INodes::<u32>::new(3, 2);
+        let opts = ONodesC::<u32>::new(3, 2, 5, 14, opt_spec.clone());
+        verify_solutions(items, opts, vec![vec![1, 3]]);
+        // Items and options need not be the same width.
+        let items = INodesM::<u64>::new(3, 2, [(1, 1); 3]);
+        let opts = OArraysC::<u32>::new(3, 2, 5, 14, opt_spec);
+        verify_solutions(items, opts, vec![vec![1, 3]]);
+
+        let spec = queens::spec(6);
+        let items = INodes::<u32>::new(spec.primary(), spec.secondary());
+        let opts = OArrays::<u32>::new(
+            spec.primary(),
+            spec.secondary(),
+            spec.size(),
+            spec.nodes(),
+            spec.options().iter().map(|o| o.iter().copied()),
+        );
+        let (items_usize, opts_usize) = (spec.items(), spec.onodes());
+        let mut chooser =
+            MRVChooser::new(choose::no_preference(), choose::first_wins());
+        let mut wide_chooser =
+            MRVChooser::new(choose::no_preference(), choose::first_wins());
+        let mut narrow = Problem::new(items, opts);
+        let mut wide = Problem::new(items_usize, opts_usize);
+        while narrow.next_solution(&mut chooser) {
+            assert!(wide.next_solution(&mut wide_chooser));
+            narrow.find_options();
+            wide.find_options();
+            assert_eq!(narrow.get_options(), wide.get_options());
+            assert_eq!(narrow.get_updates(), wide.get_updates());
+        }
+        assert!(!wide.next_solution(&mut wide_chooser));
+    }
+
+    #[test]
+    #[should_panic(expected = "color out of range")]
+    fn test_color_range() {
+        let opt_spec = [[(0, 0), (1, 1 << 31)]];
+        ONodesC::<u32>::new(1, 1, 1, 2, opt_spec);
+    }
+
+    #[test]
+    fn test_queens() {
+        // Every permutation with no two queens on a diagonal.
+        fn boards(n: usize, board: &mut Vec<usize>, out: &mut Vec<Vec<isize>>) {
+            if board.len() == n {
+                let opts = board.iter().enumerate().map(|(r, &c)| r * n + c);
+                out.push(opts.map(|k| k as isize).collect());
+            }
+            for c in 0..n {
+                let r = board.len();
+                if board
+                    .iter()
+                    .enumerate()
+                    .all(|(i, &ci)| ci != c && ci.abs_diff(c) != r - i)
+                {
+                    board.push(c);
+                    boards(n, board, out);
+                    board.pop();
+                }
+            }
+        }
+        let mut expected = Vec::new();
+        boards(8, &mut Vec::new(), &mut expected);
+        assert_eq!(expected.len(), 92);
+        let (items, opts) = queens::problem(8);
+        verify_solutions(items, opts, expected);
+    }
+
+    #[test]
+    fn test_pause() {
+        let mut chooser =
+            MRVChooser::new(choose::no_preference(), choose::first_wins());
+        let (items, opts) = queens::problem(6);
+        let mut problem = Problem::new(items, opts);
+        let mut expected = Vec::new();
+        while problem.next_solution(&mut chooser) {
+            problem.find_options();
+            expected.push(problem.o.clone());
+        }
+        let profile = problem.get_profile().to_vec();
+        assert_eq!(profile[0], 1);
+        assert_eq!(profile.len(), 7);
+        assert_eq!(profile[6], 4);
+
+        let (items, opts) = queens::problem(6);
+        let mut problem = Problem::new(items, opts);
+        let mut solutions = Vec::new();
+        let mut pauses = 0;
+        loop {
+            problem.set_node_limit(problem.nodes() + 5);
+            if problem.next_solution(&mut chooser) {
+                problem.find_options();
+                solutions.push(problem.o.clone());
+            } else if problem.is_paused() {
+                pauses += 1;
+            } else {
+                break;
+            }
+        }
+        assert!(pauses > 1);
+        assert_eq!(solutions, expected);
+        assert_eq!(problem.get_profile(), profile);
+    }
+
+    #[test]
+    fn test_dominoes() {
+        // Covers the first free cell with each domino that fits there.
+        fn tilings(
+            all: &[(usize, usize)],
+            free: &mut Vec<bool>,
+            chosen: &mut Vec<isize>,
+            out: &mut Vec<Vec<isize>>,
+        ) {
+            let Some(i) = free.iter().position(|&f| f) else {
+                out.push(chosen.clone());
+                return;
+            };
+            for (k, &(a, b)) in all.iter().enumerate() {
+                if a == i && free[b] {
+                    (free[a], free[b]) = (false, false);
+                    chosen.push(k as isize);
+                    tilings(all, free, chosen, out);
+                    chosen.pop();
+                    (free[a], free[b]) = (true, true);
+                }
+            }
+        }
+        let region = polyomino::rectangle(4, 5);
+        let mut expected = Vec::new();
+        let mut free = vec![true; region.len()];
+        let all = dominoes::dominoes(&region);
+        tilings(&all, &mut free, &mut Vec::new(), &mut expected);
+        assert_eq!(expected.len(), 95);
+        let (items, opts) = dominoes::problem(&region);
+        verify_solutions(items, opts, expected);
+    }
+
+    // Resets the search after it has visited k nodes, for each k, and
+    // checks that the structures are as they were and the search can run
+    // again.
+    fn verify_reset<I, O>(items: I, opts: O)
+    where
+        I: Items + Clone + std::fmt::Debug + PartialEq,
+        O: ODance + Clone + std::fmt::Debug + PartialEq,
+    {
+        let mut chooser =
+            MRVChooser::new(choose::no_preference(), choose::first_wins());
+        let mut problem = Problem::new(items.clone(), opts.clone());
+        let count = problem.count_solutions(&mut chooser);
+        let nodes = problem.nodes();
+        for k in 0..=nodes {
+            problem.set_node_limit(k);
+            while problem.next_solution(&mut chooser) {}
+            problem.reset();
+            assert_eq!(problem.items, items, "items not reset after {k}");
+            assert_eq!(problem.opts, opts, "options not reset after {k}");
+            assert_eq!(problem.nodes(), 0);
+        }
+        assert_eq!(problem.count_solutions(&mut chooser), count);
+        assert_eq!(problem.nodes(), nodes);
+    }
+
+    #[test]
+    fn test_reset() {
+        let (items, opts) = queens::problem(6);
+        verify_reset(items, opts);
+
+        let items = INode::make_nodes(3, 2);
+        let opt_spec = spec::taocp_xcc().options().to_vec();
+        let opts = ONodeC::make_nodes(3, 2, 5, 14, opt_spec);
+        verify_reset(items, opts);
+
+        let mut spec = partition::SetPartition::new(6);
+        for bits in [0b11u128, 0b1100, 0b110000, 0b111, 0b111000, 0b1001] {
+            spec.add_bits(bits);
+        }
+        spec.set_cover(0, 1, 2);
+        spec.set_cover(5, 0, 2);
+        let spec = spec.spec();
+        verify_reset(spec.items_m(), spec.onodes());
+    }
 }
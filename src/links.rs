@@ -1,35 +1,57 @@
 #![allow(clippy::unnecessary_cast)]
+use crate::index::{Index, node_count};
 use crate::{Items, ODance, OptSpec, Link, Count, Data};
 
+macro_rules! assert_sizes {
+    ($l:ty) => {
+        const _: () = {
+            assert!(size_of::<INode<$l>>() == 2 * size_of::<$l>());
+            assert!(size_of::<INodeM<$l>>() == 4 * size_of::<$l>());
+            assert!(size_of::<ONode<$l>>() == 3 * size_of::<$l>());
+            assert!(size_of::<ONodeC<$l>>() == 4 * size_of::<$l>());
+        };
+    };
+}
+
+assert_sizes!(u32);
+#[cfg(target_pointer_width = "64")]
+assert_sizes!(u64);
+assert_sizes!(usize);
+
+// The node types store links as L, usize unless given; make_nodes makes
+// them with usize, and new with any index type.
 #[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
-pub struct INode {
-    left: Link,
-    right: Link,
+pub struct INode<L = Link> {
+    left: L,
+    right: L,
 }
 
 impl INode {
     pub fn make_nodes(primary: Count, secondary: Count) -> INodes {
-        assert!((primary as u64) < Data::MAX as u64);
-        assert!((secondary as u64) < Data::MAX as u64);
-        let mut inodes = INodes {
-            nodes: vec![Default::default(); (primary + secondary + 2) as usize],
-            primary,
-            secondary,
-        };
-        inodes.init_links();
-        inodes
+        INodes::new(primary, secondary)
     }
 }
 
 #[derive(Clone, Debug, Eq, PartialEq)]
-pub struct INodes {
-    nodes: Vec<INode>,
+pub struct INodes<L = Link> {
+    nodes: Vec<INode<L>>,
     primary: Count,
     secondary: Count,
 }
 
-impl INodes {
-    fn get_node(&mut self, i: Link) -> &mut INode {
+impl<L: Index> INodes<L> {
+    pub fn new(primary: Count, secondary: Count) -> INodes<L> {
+        let n = primary.checked_add(secondary).expect("too many items");
+        let mut inodes = INodes {
+            nodes: vec![Default::default(); node_count::<L>(n, 0, 0)],
+            primary,
+            secondary,
+        };
+        inodes.init_links();
+        inodes
+    }
+
+    fn get_node(&mut self, i: Link) -> &mut INode<L> {
         if cfg!(feature = "unsafe-fast-index") {
             unsafe { self.nodes.get_unchecked_mut(i as usize) }
         } else {
@@ -38,7 +60,7 @@ impl INodes {
     }
 }
 
-impl Items for INodes {
+impl<L: Index> Items for INodes<L> {
     #[inline(always)]
     fn primary(&self) -> Count {
         self.primary
@@ -50,12 +72,20 @@ impl Items for INodes {
     }
 
     #[inline(always)]
-    fn llink(&mut self, i: Link) -> &mut Link {
-        &mut self.get_node(i).left
+    fn llink(&mut self, i: Link) -> Link {
+        self.get_node(i).left.link()
     }
     #[inline(always)]
-    fn rlink(&mut self, i: Link) -> &mut Link {
-        &mut self.get_node(i).right
+    fn set_llink(&mut self, i: Link, x: Link) {
+        self.get_node(i).left = L::from_link(x);
+    }
+    #[inline(always)]
+    fn rlink(&mut self, i: Link) -> Link {
+        self.get_node(i).right.link()
+    }
+    #[inline(always)]
+    fn set_rlink(&mut self, i: Link, x: Link) {
+        self.get_node(i).right = L::from_link(x);
     }
 
     #[inline(always)]
@@ -85,11 +115,11 @@ impl Items for INodes {
 }
 
 #[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
-pub struct INodeM {
-    left: Link,
-    right: Link,
-    slack: Data,
-    bound: Data,
+pub struct INodeM<L: Index = Link> {
+    left: L,
+    right: L,
+    slack: L::Signed,
+    bound: L::Signed,
 }
 
 impl INodeM {
@@ -98,32 +128,39 @@ impl INodeM {
         secondary: Count,
         ms: impl IntoIterator<Item = (Data, Data)>,
     ) -> INodesM {
-        assert!((primary as u64) < Data::MAX as u64);
-        assert!((secondary as u64) < Data::MAX as u64);
-        let n = primary + secondary;
+        INodesM::new(primary, secondary, ms)
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct INodesM<L: Index = Link> {
+    nodes: Vec<INodeM<L>>,
+    primary: Count,
+    secondary: Count,
+}
+
+impl<L: Index> INodesM<L> {
+    pub fn new(
+        primary: Count,
+        secondary: Count,
+        ms: impl IntoIterator<Item = (Data, Data)>,
+    ) -> INodesM<L> {
+        let n = primary.checked_add(secondary).expect("too many items");
         let mut inodes = INodesM {
-            nodes: vec![Default::default(); (n + 2) as usize],
+            nodes: vec![Default::default(); node_count::<L>(n, 0, 0)],
             primary,
             secondary,
         };
         for (i, (u, v)) in ms.into_iter().enumerate() {
-            inodes.nodes[i + 1].bound = v;
-            inodes.nodes[i + 1].slack = v - u;
+            assert!(L::fits(v) && L::fits(v - u), "bound out of range");
+            inodes.nodes[i + 1].bound = L::from_data(v);
+            inodes.nodes[i + 1].slack = L::from_data(v - u);
         }
         inodes.init_links();
         inodes
     }
-}
-
-#[derive(Clone, Debug, Eq, PartialEq)]
-pub struct INodesM {
-    nodes: Vec<INodeM>,
-    primary: Count,
-    secondary: Count,
-}
 
-impl INodesM {
-    fn get_node(&mut self, i: Link) -> &mut INodeM {
+    fn get_node(&mut self, i: Link) -> &mut INodeM<L> {
         if cfg!(feature = "unsafe-fast-index") {
             unsafe { self.nodes.get_unchecked_mut(i as usize) }
         } else {
@@ -132,7 +169,7 @@ impl INodesM {
     }
 }
 
-impl Items for INodesM {
+impl<L: Index> Items for INodesM<L> {
     fn primary(&self) -> Count {
         self.primary
     }
@@ -141,37 +178,47 @@ impl Items for INodesM {
         self.secondary
     }
 
-    fn llink(&mut self, i: Link) -> &mut Link {
-        &mut self.get_node(i).left
+    fn llink(&mut self, i: Link) -> Link {
+        self.get_node(i).left.link()
+    }
+
+    fn set_llink(&mut self, i: Link, x: Link) {
+        self.get_node(i).left = L::from_link(x);
+    }
+
+    fn rlink(&mut self, i: Link) -> Link {
+        self.get_node(i).right.link()
     }
 
-    fn rlink(&mut self, i: Link) -> &mut Link {
-        &mut self.get_node(i).right
+    fn set_rlink(&mut self, i: Link, x: Link) {
+        self.get_node(i).right = L::from_link(x);
     }
 
     fn bound(&mut self, i: Link) -> Data {
-        self.get_node(i).bound
+        L::data(self.get_node(i).bound)
     }
 
     fn dec_bound(&mut self, i: Link) -> Data {
         let node = self.get_node(i);
-        node.bound -= 1;
-        node.bound
+        let bound = L::data(node.bound) - 1;
+        node.bound = L::from_data(bound);
+        bound
     }
 
     fn inc_bound(&mut self, i: Link) -> Data {
         let node = self.get_node(i);
-        node.bound += 1;
-        node.bound
+        let bound = L::data(node.bound) + 1;
+        node.bound = L::from_data(bound);
+        bound
     }
 
     fn slack(&mut self, i: Link) -> Data {
-        self.get_node(i).slack
+        L::data(self.get_node(i).slack)
     }
 
     fn branch_factor(&mut self, i: Link) -> Data {
         let node = self.get_node(i);
-        node.bound.saturating_sub(node.slack)
+        L::data(node.bound).saturating_sub(L::data(node.slack))
     }
 }
 
@@ -185,64 +232,93 @@ impl OptSpec for Count {
 }
 
 #[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
-pub struct ONode {
-    hdr_info: Data,
-    up: Link,
-    down: Link,
+pub struct ONode<L: Index = Link> {
+    hdr_info: L::Signed,
+    up: L,
+    down: L,
 }
 
 impl ONode {
     pub fn make_nodes(
         np: Count,
-	ns: Count,
+        ns: Count,
         m: Count,
         l: Count,
         opt_spec: impl IntoIterator<Item = impl IntoIterator<Item = Count>>,
     ) -> ONodes {
-	assert!((m as u64) < Data::MAX as u64);
-	let n = np + ns;
-        let mut nodes = ONodes {
-            nodes: vec![Default::default(); (l + m + n + 2) as usize],
-            size: m,
-        };
-        nodes.init_links(np, ns, opt_spec);
-        nodes
+        ONodes::new(np, ns, m, l, opt_spec)
     }
 }
 
 #[derive(Clone, Debug, Eq, PartialEq)]
-pub struct ONodes {
-    nodes: Vec<ONode>,
+pub struct ONodes<L: Index = Link> {
+    nodes: Vec<ONode<L>>,
     size: Count,
 }
 
-impl ONodes {
-    fn get_node(&mut self, i: Link) -> &mut ONode {
+impl<L: Index> ONodes<L> {
+    pub fn new(
+        np: Count,
+        ns: Count,
+        m: Count,
+        l: Count,
+        opt_spec: impl IntoIterator<Item = impl IntoIterator<Item = Count>>,
+    ) -> ONodes<L> {
+        let n = np.checked_add(ns).expect("too many items");
+        let mut nodes = ONodes {
+            nodes: vec![Default::default(); node_count::<L>(n, m, l)],
+            size: m,
+        };
+        nodes.init_links(np, ns, opt_spec);
+        nodes
+    }
+
+    fn get_node(&mut self, i: Link) -> &mut ONode<L> {
         unsafe { self.nodes.get_unchecked_mut(i as usize) }
     }
 }
 
-impl ODance for ONodes {
+impl<L: Index> ODance for ONodes<L> {
     type Spec = Count;
 
     #[inline(always)]
-    fn olen(&mut self, i: Link) -> &mut Data {
-        &mut self.get_node(i).hdr_info
+    fn olen(&mut self, i: Link) -> Data {
+        L::data(self.get_node(i).hdr_info)
+    }
+
+    #[inline(always)]
+    fn set_olen(&mut self, i: Link, len: Data) {
+        self.get_node(i).hdr_info = L::from_data(len);
+    }
+
+    #[inline(always)]
+    fn top(&mut self, i: Link) -> Data {
+        L::data(self.get_node(i).hdr_info)
+    }
+
+    #[inline(always)]
+    fn set_top(&mut self, i: Link, t: Data) {
+        self.get_node(i).hdr_info = L::from_data(t);
+    }
+
+    #[inline(always)]
+    fn ulink(&mut self, i: Link) -> Link {
+        self.get_node(i).up.link()
     }
 
     #[inline(always)]
-    fn top(&mut self, i: Link) -> &mut Data {
-        &mut self.get_node(i).hdr_info
+    fn set_ulink(&mut self, i: Link, x: Link) {
+        self.get_node(i).up = L::from_link(x);
     }
 
     #[inline(always)]
-    fn ulink(&mut self, i: Link) -> &mut Link {
-        &mut self.get_node(i).up
+    fn dlink(&mut self, i: Link) -> Link {
+        self.get_node(i).down.link()
     }
 
     #[inline(always)]
-    fn dlink(&mut self, i: Link) -> &mut Link {
-        &mut self.get_node(i).down
+    fn set_dlink(&mut self, i: Link, x: Link) {
+        self.get_node(i).down = L::from_link(x);
     }
 
     #[inline(always)]
@@ -256,6 +332,10 @@ impl ODance for ONodes {
     fn size(&self) -> Count {
         self.size
     }
+
+    fn nodes(&self) -> Count {
+        self.nodes.len() as Count
+    }
 }
 
 impl OptSpec for (Count, Data) {
@@ -268,45 +348,58 @@ impl OptSpec for (Count, Data) {
 }
 
 #[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
-pub struct ONodeC {
-    hdr_info: Data,
-    up: Link,
-    down: Link,
-    color: Data,
+pub struct ONodeC<L: Index = Link> {
+    hdr_info: L::Signed,
+    up: L,
+    down: L,
+    color: L::Signed,
 }
 
 impl ONodeC {
     pub fn make_nodes(
         np: Count,
-	ns: Count,
+        ns: Count,
         m: Count,
         l: Count,
         opt_spec: impl IntoIterator<Item = impl IntoIterator<Item = (Count, Data)>>,
     ) -> ONodesC {
-	assert!((m as u64) < Data::MAX as u64);
-	let n = np + ns;
-        let mut nodes = ONodesC {
-            size: m,
-            nodes: vec![Default::default(); (l + m + n + 2) as usize],
-        };
-        nodes.init_links(np, ns, opt_spec);
-        nodes
+        ONodesC::new(np, ns, m, l, opt_spec)
     }
 }
 
 #[derive(Clone, Debug)]
-pub struct ONodesC {
-    nodes: Vec<ONodeC>,
+pub struct ONodesC<L: Index = Link> {
+    nodes: Vec<ONodeC<L>>,
     size: Count,
 }
 
-impl ONodesC {
-    fn get_node(&mut self, i: Link) -> &mut ONodeC {
+impl<L: Index> ONodesC<L> {
+    pub fn new(
+        np: Count,
+        ns: Count,
+        m: Count,
+        l: Count,
+        opt_spec: impl IntoIterator<Item = impl IntoIterator<Item = (Count, Data)>>,
+    ) -> ONodesC<L> {
+        let n = np.checked_add(ns).expect("too many items");
+        let mut nodes = ONodesC {
+            size: m,
+            nodes: vec![Default::default(); node_count::<L>(n, m, l)],
+        };
+        let opt_spec = opt_spec.into_iter().map(|opt| {
+            opt.into_iter()
+                .inspect(|&(_, c)| assert!(L::fits(c), "color out of range"))
+        });
+        nodes.init_links(np, ns, opt_spec);
+        nodes
+    }
+
+    fn get_node(&mut self, i: Link) -> &mut ONodeC<L> {
         unsafe { self.nodes.get_unchecked_mut(i as usize) }
     }
 }
 
-impl PartialEq for ONodesC {
+impl<L: Index> PartialEq for ONodesC<L> {
     fn eq(&self, other: &Self) -> bool {
         if self.size != other.size {
             return false;
@@ -323,32 +416,48 @@ impl PartialEq for ONodesC {
     }
 }
 
-impl ODance for ONodesC {
+impl<L: Index> ODance for ONodesC<L> {
     type Spec = (Count, Data);
 
-    fn olen(&mut self, i: Link) -> &mut Data {
-        &mut self.get_node(i).hdr_info
+    fn olen(&mut self, i: Link) -> Data {
+        L::data(self.get_node(i).hdr_info)
+    }
+    fn set_olen(&mut self, i: Link, len: Data) {
+        self.get_node(i).hdr_info = L::from_data(len);
     }
-    fn top(&mut self, i: Link) -> &mut Data {
-        &mut self.get_node(i).hdr_info
+    fn top(&mut self, i: Link) -> Data {
+        L::data(self.get_node(i).hdr_info)
     }
-    fn ulink(&mut self, i: Link) -> &mut Link {
-        &mut self.get_node(i).up
+    fn set_top(&mut self, i: Link, t: Data) {
+        self.get_node(i).hdr_info = L::from_data(t);
     }
-    fn dlink(&mut self, i: Link) -> &mut Link {
-        &mut self.get_node(i).down
+    fn ulink(&mut self, i: Link) -> Link {
+        self.get_node(i).up.link()
+    }
+    fn set_ulink(&mut self, i: Link, x: Link) {
+        self.get_node(i).up = L::from_link(x);
+    }
+    fn dlink(&mut self, i: Link) -> Link {
+        self.get_node(i).down.link()
+    }
+    fn set_dlink(&mut self, i: Link, x: Link) {
+        self.get_node(i).down = L::from_link(x);
     }
 
     fn get_color(&mut self, i: Link) -> Data {
-        self.get_node(i).color
+        L::data(self.get_node(i).color)
     }
     fn set_color(&mut self, i: Link, c: Data) {
-        self.get_node(i).color = c;
+        self.get_node(i).color = L::from_data(c);
     }
 
     fn size(&self) -> Count {
         self.size
     }
+
+    fn nodes(&self) -> Count {
+        self.nodes.len() as Count
+    }
 }
 
 #[cfg(test)]
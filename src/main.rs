@@ -0,0 +1,311 @@
+#![allow(clippy::unnecessary_cast)]
+use std::io::{self, Read, Write};
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+
+use algorithm_m::choose::{self, Choose, KnuthTiebreak, MRVChooser};
+use algorithm_m::dlx::Dlx;
+use algorithm_m::items::Items;
+use algorithm_m::{ODance, Problem};
+
+const USAGE: &str = "\
+usage: algorithm_m [options] [file]
+
+Solves the problem in Knuth's DLX format read from file, or from standard
+input, printing each solution as its options.
+
+options:
+  -c, --count             count the solutions without printing them
+  -n, --solutions N       stop after N solutions
+  -i, --by-item           print, for each primary item, its options
+  -t, --tiebreak T        first (the default), knuth or random
+  -s, --seed S            seed for the random tiebreak (0 by default)
+  -u, --updates           print the number of updates and nodes
+  -p, --profile           print the number of nodes at each level
+  -l, --time-limit SECS   give up after SECS seconds
+  -h, --help              print this message";
+
+// How many nodes to visit between looks at the clock.
+const NODES_PER_CHECK: u64 = 1 << 12;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Tiebreak {
+    First,
+    Knuth,
+    Random,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Args {
+    file: Option<String>,
+    count: bool,
+    solutions: Option<u64>,
+    by_item: bool,
+    tiebreak: Tiebreak,
+    seed: u64,
+    updates: bool,
+    profile: bool,
+    time_limit: Option<Duration>,
+    help: bool,
+}
+
+impl Args {
+    fn parse(args: impl IntoIterator<Item = String>) -> Result<Args, String> {
+        let mut parsed = Args {
+            file: None,
+            count: false,
+            solutions: None,
+            by_item: false,
+            tiebreak: Tiebreak::First,
+            seed: 0,
+            updates: false,
+            profile: false,
+            time_limit: None,
+            help: false,
+        };
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let mut value = |name: &str| {
+                args.next().ok_or_else(|| format!("{name} needs a value"))
+            };
+            match arg.as_str() {
+                "-c" | "--count" => parsed.count = true,
+                "-n" | "--solutions" => {
+                    let n = value(&arg)?;
+                    let n = n.parse().map_err(|_| format!("bad count {n}"))?;
+                    parsed.solutions = Some(n);
+                }
+                "-i" | "--by-item" => parsed.by_item = true,
+                "-t" | "--tiebreak" => {
+                    parsed.tiebreak = match value(&arg)?.as_str() {
+                        "first" => Tiebreak::First,
+                        "knuth" => Tiebreak::Knuth,
+                        "random" => Tiebreak::Random,
+                        t => return Err(format!("unknown tiebreak {t}")),
+                    };
+                }
+                "-s" | "--seed" => {
+                    let s = value(&arg)?;
+                    parsed.seed =
+                        s.parse().map_err(|_| format!("bad seed {s}"))?;
+                }
+                "-u" | "--updates" => parsed.updates = true,
+                "-p" | "--profile" => parsed.profile = true,
+                "-l" | "--time-limit" => {
+                    let t = value(&arg)?;
+                    let secs = t
+                        .parse()
+                        .ok()
+                        .and_then(|t| Duration::try_from_secs_f64(t).ok())
+                        .ok_or_else(|| format!("bad time limit {t}"))?;
+                    parsed.time_limit = Some(secs);
+                }
+                "-h" | "--help" => parsed.help = true,
+                _ if arg.starts_with('-') && arg != "-" => {
+                    return Err(format!("unknown option {arg}"));
+                }
+                _ if parsed.file.is_some() => {
+                    return Err("more than one file".to_string());
+                }
+                _ => parsed.file = Some(arg),
+            }
+        }
+        Ok(parsed)
+    }
+}
+
+fn main() -> ExitCode {
+    let args = match Args::parse(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("{err}\n\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+    if args.help {
+        println!("{USAGE}");
+        return ExitCode::SUCCESS;
+    }
+    let mut input = String::new();
+    let read = match args.file.as_deref() {
+        None | Some("-") => io::stdin().read_to_string(&mut input).map(|_| ()),
+        Some(file) => std::fs::read_to_string(file).map(|s| input = s),
+    };
+    if let Err(err) = read {
+        eprintln!("cannot read input: {err}");
+        return ExitCode::FAILURE;
+    }
+    let dlx = match Dlx::parse(&input) {
+        Ok(dlx) => dlx,
+        Err(err) => {
+            eprintln!("{err}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let result = match dlx.spec().uncolored() {
+        Some(spec) => with_items(&args, &dlx, spec.onodes()),
+        None => with_items(&args, &dlx, dlx.spec().onodes_c()),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("{err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+// Picks the item nodes and chooser; multiplicities and Knuth's tiebreak
+// need INodesM.
+fn with_items<O: ODance>(args: &Args, dlx: &Dlx, opts: O) -> io::Result<()> {
+    let spec = dlx.spec();
+    let prefer = choose::no_preference;
+    if spec.has_bounds() || args.tiebreak == Tiebreak::Knuth {
+        let problem = Problem::new(spec.items_m(), opts);
+        match args.tiebreak {
+            Tiebreak::First => {
+                let chooser = MRVChooser::new(prefer(), choose::first_wins());
+                solve(args, dlx, problem, chooser)
+            }
+            Tiebreak::Knuth => {
+                let chooser = MRVChooser::new(prefer(), KnuthTiebreak());
+                solve(args, dlx, problem, chooser)
+            }
+            Tiebreak::Random => {
+                let tiebreak = choose::random_tiebreak(args.seed);
+                solve(args, dlx, problem, MRVChooser::new(prefer(), tiebreak))
+            }
+        }
+    } else {
+        let problem = Problem::new(spec.items(), opts);
+        match args.tiebreak {
+            Tiebreak::First | Tiebreak::Knuth => {
+                let chooser = MRVChooser::new(prefer(), choose::first_wins());
+                solve(args, dlx, problem, chooser)
+            }
+            Tiebreak::Random => {
+                let tiebreak = choose::random_tiebreak(args.seed);
+                solve(args, dlx, problem, MRVChooser::new(prefer(), tiebreak))
+            }
+        }
+    }
+}
+
+fn solve<I, O, C>(
+    args: &Args,
+    dlx: &Dlx,
+    mut problem: Problem<I, O>,
+    mut chooser: C,
+) -> io::Result<()>
+where
+    I: Items,
+    O: ODance,
+    C: Choose<I>,
+{
+    let mut out = io::BufWriter::new(io::stdout().lock());
+    let start = Instant::now();
+    let mut count: u64 = 0;
+    let mut timed_out = false;
+    while args.solutions.is_none_or(|n| count < n) {
+        if let Some(limit) = args.time_limit {
+            if start.elapsed() >= limit {
+                timed_out = true;
+                break;
+            }
+            problem.set_node_limit(problem.nodes() + NODES_PER_CHECK);
+        }
+        if problem.next_solution(&mut chooser) {
+            count += 1;
+            if !args.count {
+                print_solution(
+                    &mut out,
+                    dlx,
+                    args.by_item,
+                    count,
+                    &mut problem,
+                )?;
+            }
+        } else if !problem.is_paused() {
+            break;
+        }
+    }
+    out.flush()?;
+
+    let mut err = io::stderr().lock();
+    if timed_out {
+        writeln!(err, "time limit reached")?;
+    }
+    let plural = if count == 1 { "" } else { "s" };
+    writeln!(err, "{count} solution{plural}")?;
+    if args.updates {
+        writeln!(
+            err,
+            "{} updates, {} nodes",
+            problem.get_updates(),
+            problem.nodes()
+        )?;
+    }
+    if args.profile {
+        for (level, nodes) in problem.get_profile().iter().enumerate() {
+            writeln!(err, "{level:3}: {nodes}")?;
+        }
+    }
+    Ok(())
+}
+
+fn print_solution<I: Items, O: ODance>(
+    out: &mut impl Write,
+    dlx: &Dlx,
+    by_item: bool,
+    count: u64,
+    problem: &mut Problem<I, O>,
+) -> io::Result<()> {
+    writeln!(out, "{count}:")?;
+    if !by_item {
+        let choices = problem.find_choices();
+        for line in dlx.format_solution(&choices).lines() {
+            writeln!(out, " {line}")?;
+        }
+        return Ok(());
+    }
+    problem.find_options();
+    let spec = dlx.spec();
+    for (i, name) in dlx.items()[..dlx.primary()].iter().enumerate() {
+        for &k in problem.get_options() {
+            let opt = &spec.options()[k as usize];
+            if opt.iter().any(|&(j, _)| j as usize == i) {
+                writeln!(out, " {name}: {}", dlx.format_option(k as usize))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Args, String> {
+        Args::parse(args.iter().map(|s| s.to_string()))
+    }
+
+    #[test]
+    fn test_args() {
+        let args = parse(&["-c", "-t", "random", "--seed", "7", "in.dlx"]);
+        let args = args.unwrap();
+        assert!(args.count && !args.by_item);
+        assert_eq!(args.tiebreak, Tiebreak::Random);
+        assert_eq!(args.seed, 7);
+        assert_eq!(args.file.as_deref(), Some("in.dlx"));
+
+        let args = parse(&["-n", "3", "-l", "1.5", "-u", "-p"]).unwrap();
+        assert_eq!(args.solutions, Some(3));
+        assert_eq!(args.time_limit, Some(Duration::from_millis(1500)));
+        assert!(args.updates && args.profile && args.file.is_none());
+
+        assert_eq!(parse(&["-n"]), Err("-n needs a value".to_string()));
+        assert_eq!(parse(&["-l", "-1"]), Err("bad time limit -1".to_string()));
+        assert_eq!(parse(&["-t", "x"]), Err("unknown tiebreak x".to_string()));
+        assert_eq!(parse(&["a", "b"]), Err("more than one file".to_string()));
+    }
+}
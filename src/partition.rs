@@ -0,0 +1,162 @@
+#![allow(clippy::unnecessary_cast)]
+use crate::choose::{self, KnuthTiebreak, MRVChooser, NoPreference};
+use crate::links::{INodesM, ONodes};
+use crate::spec::Spec;
+use crate::{Count, Data, Problem};
+
+// Choices of subsets of the universe 0..universe that cover every element
+// exactly once, or between the bounds set for it.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SetPartition {
+    bounds: Vec<(Data, Data)>,
+    subsets: Vec<Vec<usize>>,
+}
+
+impl SetPartition {
+    pub fn new(universe: usize) -> SetPartition {
+        SetPartition {
+            bounds: vec![(1, 1); universe],
+            subsets: Vec::new(),
+        }
+    }
+
+    pub fn universe(&self) -> usize {
+        self.bounds.len()
+    }
+
+    // Repeated elements count once.
+    pub fn add_subset(&mut self, elems: impl IntoIterator<Item = usize>) {
+        let mut elems: Vec<usize> = elems.into_iter().collect();
+        elems.sort();
+        elems.dedup();
+        assert!(!elems.is_empty(), "empty subset");
+        assert!(
+            elems.iter().all(|&e| e < self.universe()),
+            "no such element"
+        );
+        self.subsets.push(elems);
+    }
+
+    // Bit e of bits stands for element e.
+    pub fn add_bits(&mut self, bits: u128) {
+        self.add_subset((0..128).filter(|&e| bits & (1 << e) != 0));
+    }
+
+    pub fn subsets(&self) -> &[Vec<usize>] {
+        &self.subsets
+    }
+
+    // Element e must be covered at least lo and at most hi times.
+    pub fn set_cover(&mut self, e: usize, lo: usize, hi: usize) {
+        assert!(lo <= hi && hi > 0, "invalid bounds");
+        self.bounds[e] = (lo as Data, hi as Data);
+    }
+
+    // Elements are primary items; option k is subset k.
+    pub fn spec(&self) -> Spec<Count> {
+        let mut spec = Spec::new(self.universe() as Count, 0);
+        for (e, &(lo, hi)) in self.bounds.iter().enumerate() {
+            spec.set_bounds(e as Count, lo, hi);
+        }
+        for subset in &self.subsets {
+            spec.add_option(subset.iter().map(|&e| e as Count));
+        }
+        spec
+    }
+
+    // The indexes of the chosen subsets, in increasing order.
+    pub fn solutions(&self) -> Solutions {
+        let spec = self.spec();
+        Solutions {
+            problem: Problem::new(spec.items_m(), spec.onodes()),
+            chooser: MRVChooser::new(choose::no_preference(), KnuthTiebreak()),
+            done: false,
+        }
+    }
+}
+
+pub struct Solutions {
+    problem: Problem<INodesM, ONodes>,
+    chooser: MRVChooser<NoPreference, KnuthTiebreak>,
+    done: bool,
+}
+
+impl Iterator for Solutions {
+    type Item = Vec<usize>;
+
+    fn next(&mut self) -> Option<Vec<usize>> {
+        if self.done || !self.problem.next_solution(&mut self.chooser) {
+            self.done = true;
+            return None;
+        }
+        self.problem.find_options();
+        let mut chosen: Vec<usize> = self
+            .problem
+            .get_options()
+            .iter()
+            .map(|&k| k as usize)
+            .collect();
+        chosen.sort();
+        Some(chosen)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    // TAocp Vol. 4B p. 66
+    fn test_partition() {
+        let mut partition = SetPartition::new(7);
+        for subset in [
+            &[2, 4][..],
+            &[0, 3, 6],
+            &[1, 2, 5],
+            &[0, 3, 5],
+            &[1, 6],
+            &[3, 4, 6],
+        ] {
+            partition.add_subset(subset.iter().copied());
+        }
+        let solutions: Vec<Vec<usize>> = partition.solutions().collect();
+        assert_eq!(solutions, [[0, 3, 4]]);
+
+        let mut bits = SetPartition::new(7);
+        for subset in partition.subsets() {
+            bits.add_bits(subset.iter().map(|&e| 1 << e).sum());
+        }
+        assert_eq!(bits, partition);
+    }
+
+    #[test]
+    fn test_bounds() {
+        let subsets = [0b001, 0b010, 0b100, 0b011, 0b110, 0b111];
+        let bounds = [(1, 2), (0, 1), (2, 3)];
+        let mut partition = SetPartition::new(3);
+        for &bits in &subsets {
+            partition.add_bits(bits);
+        }
+        for (e, &(lo, hi)) in bounds.iter().enumerate() {
+            partition.set_cover(e, lo, hi);
+        }
+
+        let mut expected = Vec::new();
+        for choice in 0u32..1 << subsets.len() {
+            let chosen: Vec<usize> = (0..subsets.len())
+                .filter(|&k| choice & (1 << k) != 0)
+                .collect();
+            let fits = bounds.iter().enumerate().all(|(e, &(lo, hi))| {
+                let n = chosen.iter().filter(|&&k| subsets[k] & (1 << e) != 0);
+                (lo..=hi).contains(&n.count())
+            });
+            if fits {
+                expected.push(chosen);
+            }
+        }
+        let mut found: Vec<Vec<usize>> = partition.solutions().collect();
+        found.sort();
+        expected.sort();
+        assert_eq!(found, expected);
+    }
+}
@@ -0,0 +1,441 @@
+#![allow(clippy::unnecessary_cast)]
+use std::collections::{HashMap, HashSet};
+
+use crate::Count;
+use crate::spec::Spec;
+
+pub type Cell = (i32, i32);
+pub type Cube = (i32, i32, i32);
+
+// The twelve pentominoes, in Conway's naming.
+pub fn pentominoes() -> Vec<(char, Vec<Cell>)> {
+    vec![
+        ('F', vec![(1, 0), (2, 0), (0, 1), (1, 1), (1, 2)]),
+        ('I', vec![(0, 0), (0, 1), (0, 2), (0, 3), (0, 4)]),
+        ('L', vec![(0, 0), (0, 1), (0, 2), (0, 3), (1, 3)]),
+        ('N', vec![(1, 0), (1, 1), (0, 2), (1, 2), (0, 3)]),
+        ('P', vec![(0, 0), (1, 0), (0, 1), (1, 1), (0, 2)]),
+        ('T', vec![(0, 0), (1, 0), (2, 0), (1, 1), (1, 2)]),
+        ('U', vec![(0, 0), (2, 0), (0, 1), (1, 1), (2, 1)]),
+        ('V', vec![(0, 0), (0, 1), (0, 2), (1, 2), (2, 2)]),
+        ('W', vec![(0, 0), (0, 1), (1, 1), (1, 2), (2, 2)]),
+        ('X', vec![(1, 0), (0, 1), (1, 1), (2, 1), (1, 2)]),
+        ('Y', vec![(1, 0), (0, 1), (1, 1), (1, 2), (1, 3)]),
+        ('Z', vec![(0, 0), (1, 0), (1, 1), (1, 2), (2, 2)]),
+    ]
+}
+
+// The seven Soma pieces; A and B are mirror images of each other.
+pub fn soma() -> Vec<(char, Vec<Cube>)> {
+    vec![
+        ('V', vec![(0, 0, 0), (1, 0, 0), (0, 1, 0)]),
+        ('L', vec![(0, 0, 0), (1, 0, 0), (2, 0, 0), (0, 1, 0)]),
+        ('T', vec![(0, 0, 0), (1, 0, 0), (2, 0, 0), (1, 1, 0)]),
+        ('Z', vec![(0, 0, 0), (1, 0, 0), (1, 1, 0), (2, 1, 0)]),
+        ('A', vec![(0, 0, 0), (1, 0, 0), (1, 1, 0), (1, 1, 1)]),
+        ('B', vec![(0, 0, 1), (1, 0, 1), (1, 1, 1), (1, 1, 0)]),
+        ('P', vec![(0, 0, 0), (1, 0, 0), (0, 1, 0), (0, 0, 1)]),
+    ]
+}
+
+pub fn rectangle(width: i32, height: i32) -> Vec<Cell> {
+    (0..height)
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .collect()
+}
+
+pub fn cuboid(x: i32, y: i32, z: i32) -> Vec<Cube> {
+    (0..z)
+        .flat_map(|k| (0..y).flat_map(move |j| (0..x).map(move |i| (i, j, k))))
+        .collect()
+}
+
+// A 2D region drawn row by row: '.' is a cell of the region, anything
+// else but whitespace is a hole.
+pub fn picture(s: &str) -> Vec<Cell> {
+    let mut cells = Vec::new();
+    let lines = s.lines().map(str::trim).filter(|l| !l.is_empty());
+    for (y, line) in lines.enumerate() {
+        let chars = line.chars().filter(|ch| !ch.is_whitespace());
+        for (x, ch) in chars.enumerate() {
+            if ch == '.' {
+                cells.push((x as i32, y as i32));
+            }
+        }
+    }
+    cells
+}
+
+// A piece in one orientation, moved into the region.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Placement {
+    pub piece: usize,
+    pub cells: Vec<Cube>,
+}
+
+// Packs pieces into a region. Each option is a piece followed by the cells
+// it covers, and is numbered as in placements().
+#[derive(Clone, Debug)]
+pub struct Packing {
+    pieces: Vec<Vec<Cube>>,
+    region: Vec<Cube>,
+    planar: bool,
+    reflections: bool,
+    at_most_once: bool,
+    reduce: bool,
+}
+
+impl Packing {
+    pub fn new(pieces: &[Vec<Cube>], region: &[Cube]) -> Packing {
+        let planar = pieces.iter().flatten().chain(region).all(|c| c.2 == 0);
+        let mut region = region.to_vec();
+        region.sort();
+        region.dedup();
+        Packing {
+            pieces: pieces.to_vec(),
+            region,
+            planar,
+            reflections: true,
+            at_most_once: false,
+            reduce: false,
+        }
+    }
+
+    pub fn planar(pieces: &[Vec<Cell>], region: &[Cell]) -> Packing {
+        let pieces: Vec<Vec<Cube>> = pieces.iter().map(|p| lift(p)).collect();
+        Packing::new(&pieces, &lift(region))
+    }
+
+    // Whether pieces may be turned over; on by default.
+    pub fn set_reflections(&mut self, reflections: bool) {
+        self.reflections = reflections;
+    }
+
+    // Makes the pieces secondary items, so a packing need not use them
+    // all.
+    pub fn set_at_most_once(&mut self, at_most_once: bool) {
+        self.at_most_once = at_most_once;
+    }
+
+    // Keeps only one placement of the first piece from each class of
+    // placements that the symmetries of the region map onto each other, so
+    // solutions that are rotations or reflections of each other are found
+    // once. A solution in which the first piece is left in place by some
+    // symmetry may still be found more than once; putting a piece without
+    // such placements first avoids this.
+    pub fn set_symmetry_reduction(&mut self, reduce: bool) {
+        self.reduce = reduce;
+    }
+
+    pub fn region(&self) -> &[Cube] {
+        &self.region
+    }
+
+    // The placement made by each option, in option order.
+    pub fn placements(&self) -> Vec<Placement> {
+        let cells: HashSet<Cube> = self.region.iter().copied().collect();
+        let symmetries = self.symmetries();
+        let mut placements = Vec::new();
+        for (k, piece) in self.pieces.iter().enumerate() {
+            for shape in self.orientations(piece) {
+                for &target in &self.region {
+                    let d = sub(target, shape[0]);
+                    let mut moved: Vec<Cube> =
+                        shape.iter().map(|&c| add(c, d)).collect();
+                    if !moved.iter().all(|c| cells.contains(c)) {
+                        continue;
+                    }
+                    moved.sort();
+                    if k == 0
+                        && self.reduce
+                        && symmetries.iter().any(|s| s.apply(&moved) < moved)
+                    {
+                        continue;
+                    }
+                    placements.push(Placement { piece: k, cells: moved });
+                }
+            }
+        }
+        placements
+    }
+
+    // Items are the pieces, then the cells of the region in sorted order;
+    // with set_at_most_once the cells come first and the pieces are
+    // secondary.
+    pub fn spec(&self) -> Spec<Count> {
+        let np = self.pieces.len();
+        let nc = self.region.len();
+        let (mut spec, piece0, cell0) = if self.at_most_once {
+            (Spec::new(nc as Count, np as Count), nc, 0)
+        } else {
+            (Spec::new((np + nc) as Count, 0), 0, np)
+        };
+        for p in self.placements() {
+            let cells = p.cells.iter().map(|c| {
+                (cell0 + self.region.binary_search(c).unwrap()) as Count
+            });
+            let piece = (piece0 + p.piece) as Count;
+            spec.add_option(std::iter::once(piece).chain(cells));
+        }
+        spec
+    }
+
+    // Draws a solution with names[k] for the cells of piece k; cells left
+    // empty are '.' and holes are spaces. The layers of a 3D region are
+    // drawn one after another, separated by blank lines.
+    pub fn render(&self, options: &[isize], names: &[char]) -> String {
+        let placements = self.placements();
+        let mut filled = HashMap::new();
+        for &k in options {
+            let p = &placements[k as usize];
+            for &c in &p.cells {
+                filled.insert(c, names[p.piece]);
+            }
+        }
+        let lo = corner(&self.region);
+        let hi = self
+            .region
+            .iter()
+            .fold(lo, |a, &c| (a.0.max(c.0), a.1.max(c.1), a.2.max(c.2)));
+        let mut s = String::new();
+        for z in lo.2..=hi.2 {
+            if z != lo.2 {
+                s.push('\n');
+            }
+            for y in lo.1..=hi.1 {
+                for x in lo.0..=hi.0 {
+                    let c = (x, y, z);
+                    s.push(match filled.get(&c) {
+                        Some(&ch) => ch,
+                        None if self.region.binary_search(&c).is_ok() => '.',
+                        None => ' ',
+                    });
+                }
+                s.push('\n');
+            }
+        }
+        s
+    }
+
+    // The distinct orientations of a piece, each shifted to the origin.
+    pub fn orientations(&self, cells: &[Cube]) -> Vec<Vec<Cube>> {
+        let mut shapes: Vec<Vec<Cube>> = Vec::new();
+        for t in self.transforms() {
+            let shape = normalize(&t.apply(cells));
+            if !shapes.contains(&shape) {
+                shapes.push(shape);
+            }
+        }
+        shapes
+    }
+
+    // Rotations, and reflections if allowed; planar problems stay in the
+    // plane.
+    fn transforms(&self) -> Vec<Transform> {
+        Transform::all()
+            .filter(|t| !self.planar || (t.perm[2] == 2 && t.sign[2] == 1))
+            .filter(|t| self.reflections || t.det() == 1)
+            .collect()
+    }
+
+    // The transforms other than the identity that map the region onto
+    // itself, each with the shift that puts it back in place.
+    fn symmetries(&self) -> Vec<Symmetry> {
+        let origin = corner(&self.region);
+        let region = normalize(&self.region);
+        self.transforms()
+            .into_iter()
+            .filter(|t| !t.is_identity())
+            .filter_map(|t| {
+                let image = t.apply(&self.region);
+                let shift = sub(origin, corner(&image));
+                (normalize(&image) == region).then_some(Symmetry { t, shift })
+            })
+            .collect()
+    }
+}
+
+// Permutes the axes and flips their signs: axis i of the image is
+// sign[i] times axis perm[i] of the original.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct Transform {
+    perm: [usize; 3],
+    sign: [i32; 3],
+}
+
+impl Transform {
+    fn all() -> impl Iterator<Item = Transform> {
+        const PERMS: [[usize; 3]; 6] = [
+            [0, 1, 2],
+            [0, 2, 1],
+            [1, 0, 2],
+            [1, 2, 0],
+            [2, 0, 1],
+            [2, 1, 0],
+        ];
+        PERMS.into_iter().flat_map(|perm| {
+            (0..8).map(move |s| Transform {
+                perm,
+                sign: [0, 1, 2].map(|i| if s & (1 << i) != 0 { -1 } else { 1 }),
+            })
+        })
+    }
+
+    fn det(&self) -> i32 {
+        let [a, b, c] = self.perm;
+        let inversions = (a > b) as i32 + (a > c) as i32 + (b > c) as i32;
+        let parity = if inversions % 2 == 0 { 1 } else { -1 };
+        parity * self.sign.iter().product::<i32>()
+    }
+
+    fn is_identity(&self) -> bool {
+        self.perm == [0, 1, 2] && self.sign == [1, 1, 1]
+    }
+
+    fn apply(&self, cells: &[Cube]) -> Vec<Cube> {
+        cells
+            .iter()
+            .map(|&(x, y, z)| {
+                let v = [x, y, z];
+                let [x, y, z] =
+                    [0, 1, 2].map(|i| self.sign[i] * v[self.perm[i]]);
+                (x, y, z)
+            })
+            .collect()
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Symmetry {
+    t: Transform,
+    shift: Cube,
+}
+
+impl Symmetry {
+    // The sorted image of a placement.
+    fn apply(&self, cells: &[Cube]) -> Vec<Cube> {
+        let mut image: Vec<Cube> = self
+            .t
+            .apply(cells)
+            .into_iter()
+            .map(|c| add(c, self.shift))
+            .collect();
+        image.sort();
+        image
+    }
+}
+
+fn lift(cells: &[Cell]) -> Vec<Cube> {
+    cells.iter().map(|&(x, y)| (x, y, 0)).collect()
+}
+
+fn add(a: Cube, b: Cube) -> Cube {
+    (a.0 + b.0, a.1 + b.1, a.2 + b.2)
+}
+
+fn sub(a: Cube, b: Cube) -> Cube {
+    (a.0 - b.0, a.1 - b.1, a.2 - b.2)
+}
+
+fn corner(cells: &[Cube]) -> Cube {
+    cells.iter().fold((i32::MAX, i32::MAX, i32::MAX), |a, &c| {
+        (a.0.min(c.0), a.1.min(c.1), a.2.min(c.2))
+    })
+}
+
+fn normalize(cells: &[Cube]) -> Vec<Cube> {
+    let c0 = corner(cells);
+    let mut cells: Vec<Cube> = cells.iter().map(|&c| sub(c, c0)).collect();
+    cells.sort();
+    cells
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Problem;
+    use crate::choose::{self, MRVChooser};
+
+    fn count(packing: &Packing) -> usize {
+        let spec = packing.spec();
+        let mut problem = Problem::new(spec.items(), spec.onodes());
+        let mut chooser =
+            MRVChooser::new(choose::no_preference(), choose::first_wins());
+        let mut count = 0;
+        while problem.next_solution(&mut chooser) {
+            count += 1;
+        }
+        count
+    }
+
+    #[test]
+    fn test_pentominoes() {
+        let pieces: Vec<_> = pentominoes().into_iter().map(|p| p.1).collect();
+        let mut packing = Packing::planar(&pieces, &rectangle(20, 3));
+        let counts: Vec<usize> = pieces
+            .iter()
+            .map(|p| packing.orientations(&lift(p)).len())
+            .collect();
+        assert_eq!(counts, [8, 2, 8, 8, 8, 4, 4, 4, 4, 1, 8, 4]);
+        assert_eq!(packing.symmetries().len(), 3);
+
+        // Two tilings, each in four orientations.
+        assert_eq!(count(&packing), 8);
+        packing.set_symmetry_reduction(true);
+        assert_eq!(count(&packing), 2);
+    }
+
+    #[test]
+    fn test_holes() {
+        // A ring of eight cells has two domino tilings, each found once
+        // for every order of the four dominoes.
+        let region = picture("...\n.#.\n...");
+        assert_eq!(region.len(), 8);
+        let packing = Packing::planar(&vec![rectangle(2, 1); 4], &region);
+        assert_eq!(count(&packing), 48);
+
+        let spec = packing.spec();
+        let mut problem = Problem::new(spec.items(), spec.onodes());
+        let mut chooser =
+            MRVChooser::new(choose::no_preference(), choose::first_wins());
+        assert!(problem.next_solution(&mut chooser));
+        problem.find_options();
+        let s = packing.render(problem.get_options(), &['a', 'b', 'c', 'd']);
+        assert_eq!(s, "aad\nb d\nbcc\n");
+        assert_eq!(packing.render(&[], &[]), "...\n. .\n...\n");
+    }
+
+    #[test]
+    fn test_at_most_once() {
+        // A monomino, a domino and a straight tromino can't all fit in a
+        // 1x4 strip, but the monomino and the tromino can, in two ways.
+        let pieces = vec![vec![(0, 0)], rectangle(2, 1), rectangle(3, 1)];
+        let mut packing = Packing::planar(&pieces, &rectangle(4, 1));
+        assert_eq!(count(&packing), 0);
+        packing.set_at_most_once(true);
+        assert_eq!(count(&packing), 2);
+        packing.set_symmetry_reduction(true);
+        assert_eq!(count(&packing), 1);
+    }
+
+    #[test]
+    fn test_soma() {
+        let mut pieces: Vec<_> = soma().into_iter().map(|p| p.1).collect();
+        let mut packing = Packing::new(&pieces, &cuboid(3, 3, 3));
+        packing.set_reflections(false);
+        assert_eq!(packing.symmetries().len(), 23);
+        let counts: Vec<usize> = pieces
+            .iter()
+            .map(|p| packing.orientations(p).len())
+            .collect();
+        assert_eq!(counts, [12, 24, 12, 12, 12, 12, 8]);
+
+        // No rotation leaves a placement of L in place, so with L first
+        // each of the 11520 solutions is found once up to rotation.
+        pieces.swap(0, 1);
+        let mut packing = Packing::new(&pieces, &cuboid(3, 3, 3));
+        packing.set_reflections(false);
+        packing.set_symmetry_reduction(true);
+        assert_eq!(count(&packing) * 24, 11520);
+    }
+}
@@ -0,0 +1,172 @@
+#![allow(clippy::unnecessary_cast)]
+use crate::choose::Preference;
+use crate::links::{INodes, ONodes};
+use crate::spec::Spec;
+use crate::{Count, Data, Link};
+
+// Rows and columns are primary items, the 2n - 1 diagonals in each
+// direction are secondary. Option r * n + c puts a queen on row r,
+// column c.
+//
+// Rows and columns are listed in "organ-pipe" order, from the middle of
+// the board outwards, and alternate: the middle row, the middle column,
+// the row above it, the column left of it, the row below, and so on.
+pub fn spec(n: usize) -> Spec<Count> {
+    assert!(n > 0);
+    let mut spec = Spec::new((2 * n) as Count, (4 * n - 2) as Count);
+    for r in 0..n {
+        for c in 0..n {
+            spec.add_option(items(n, r, c));
+        }
+    }
+    spec
+}
+
+pub fn problem(n: usize) -> (INodes, ONodes) {
+    let spec = spec(n);
+    (spec.items(), spec.onodes())
+}
+
+// Every row and column gets exactly k queens and every diagonal at most
+// k, so all items are primary with multiplicities.
+pub fn multi(n: usize, k: usize) -> Spec<Count> {
+    assert!(n > 0 && k > 0 && k <= n);
+    let mut spec = Spec::new((6 * n - 2) as Count, 0);
+    for i in 0..(6 * n - 2) {
+        if i < 2 * n {
+            spec.set_bounds(i as Count, k as Data, k as Data);
+        } else {
+            spec.set_bounds(i as Count, 0, k as Data);
+        }
+    }
+    for r in 0..n {
+        for c in 0..n {
+            spec.add_option(items(n, r, c));
+        }
+    }
+    spec
+}
+
+// The column of the queen in each row.
+pub fn board(n: usize, options: &[isize]) -> Vec<usize> {
+    let mut board = vec![0; n];
+    for &k in options {
+        let k = k as usize;
+        board[k / n] = k % n;
+    }
+    board
+}
+
+pub fn render(board: &[usize]) -> String {
+    let mut s = String::new();
+    for &c in board {
+        for j in 0..board.len() {
+            s.push(if j == c { 'Q' } else { '.' });
+        }
+        s.push('\n');
+    }
+    s
+}
+
+// Prefers the rows and columns whose organ-pipe position is below width,
+// that is, those nearest the middle of the board.
+pub struct MiddlePreference {
+    limit: Link,
+}
+
+impl MiddlePreference {
+    pub fn new(width: usize) -> MiddlePreference {
+        MiddlePreference { limit: (2 * width) as Link }
+    }
+}
+
+impl Preference for MiddlePreference {
+    fn is_preferred(&self, p: Link) -> bool {
+        // Internal item numbers are 1-based.
+        p <= self.limit
+    }
+}
+
+// Position of row or column i in organ-pipe order.
+fn organ_pipe(n: usize, i: usize) -> usize {
+    let mid = n / 2;
+    if i >= mid {
+        2 * (i - mid)
+    } else {
+        2 * (mid - i) - 1
+    }
+}
+
+fn items(n: usize, r: usize, c: usize) -> [Count; 4] {
+    [
+        (2 * organ_pipe(n, r)) as Count,
+        (2 * organ_pipe(n, c) + 1) as Count,
+        (2 * n + r + c) as Count,
+        (5 * n - 2 + r - c) as Count,
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Problem;
+    use crate::choose::{self, MRVChooser};
+
+    fn is_valid(board: &[usize]) -> bool {
+        (0..board.len()).all(|i| {
+            (0..i).all(|j| {
+                board[i] != board[j] && board[i].abs_diff(board[j]) != i - j
+            })
+        })
+    }
+
+    #[test]
+    fn test_queens() {
+        let (items, opts) = problem(8);
+        let mut problem = Problem::new(items, opts);
+        let mut chooser =
+            MRVChooser::new(choose::no_preference(), choose::first_wins());
+        let mut boards = Vec::new();
+        while problem.next_solution(&mut chooser) {
+            problem.find_options();
+            let board = board(8, problem.get_options());
+            assert!(is_valid(&board), "invalid board");
+            boards.push(board);
+        }
+        boards.sort();
+        boards.dedup();
+        assert_eq!(boards.len(), 92);
+        assert_eq!(render(&[1, 3, 0, 2]), ".Q..\n...Q\nQ...\n..Q.\n");
+    }
+
+    #[test]
+    fn test_counts() {
+        let counts = [1, 0, 0, 2, 10, 4, 40, 92, 352];
+        for (n, &expected) in (1..).zip(&counts) {
+            let spec = spec(n);
+            let mut problem = Problem::new(spec.items(), spec.onodes());
+            let mut chooser = MRVChooser::new(
+                MiddlePreference::new(n / 2),
+                choose::first_wins(),
+            );
+            let mut count = 0;
+            while problem.next_solution(&mut chooser) {
+                count += 1;
+            }
+            assert_eq!(count, expected, "{n} queens");
+        }
+    }
+
+    #[test]
+    fn test_organ_pipe() {
+        let order: Vec<usize> = (0..8).map(|i| organ_pipe(8, i)).collect();
+        assert_eq!(order, [7, 5, 3, 1, 0, 2, 4, 6]);
+        let order: Vec<usize> = (0..5).map(|i| organ_pipe(5, i)).collect();
+        assert_eq!(order, [3, 1, 0, 2, 4]);
+
+        let prefer = MiddlePreference::new(2);
+        let preferred: Vec<Link> =
+            (1..=16).filter(|&p| prefer.is_preferred(p)).collect();
+        assert_eq!(preferred, [1, 2, 3, 4]);
+    }
+}
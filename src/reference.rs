@@ -0,0 +1,246 @@
+#![allow(clippy::unnecessary_cast)]
+use crate::choose::{self, KnuthTiebreak, MRVChooser};
+use crate::random::Rng;
+use crate::spec::Spec;
+use crate::verify::verify_solution;
+use crate::{Count, Data, Problem};
+
+// Every solution of a small problem, found by trying every set of
+// options with verify_solution. Solutions are sorted, and listed in
+// order.
+pub fn solutions(spec: &Spec<(Count, Data)>) -> Vec<Vec<usize>> {
+    let m = spec.options().len();
+    assert!(m < 24, "too many options to try every set");
+    let mut found = Vec::new();
+    for set in 0..1u32 << m {
+        let chosen: Vec<usize> =
+            (0..m).filter(|&k| set & (1 << k) != 0).collect();
+        if verify_solution(spec, &chosen).is_ok() {
+            found.push(chosen);
+        }
+    }
+    found.sort();
+    found
+}
+
+// The solutions Algorithm M finds, in the same form.
+pub fn search(spec: &Spec<(Count, Data)>) -> Vec<Vec<usize>> {
+    let mut chooser = MRVChooser::new(choose::no_preference(), KnuthTiebreak());
+    let mut problem = Problem::new(spec.items_m(), spec.onodes_c());
+    let mut found = Vec::new();
+    while problem.next_solution(&mut chooser) {
+        problem.find_options();
+        let mut chosen: Vec<usize> =
+            problem.get_options().iter().map(|&k| k as usize).collect();
+        chosen.sort();
+        found.push(chosen);
+    }
+    found.sort();
+    found
+}
+
+// Checks that Algorithm M finds exactly the solutions the reference
+// solver does, and says how they differ if not.
+pub fn compare(spec: &Spec<(Count, Data)>) -> Result<(), String> {
+    let expected = solutions(spec);
+    let found = search(spec);
+    if found == expected {
+        return Ok(());
+    }
+    let missing = expected.iter().filter(|s| !found.contains(s));
+    let missing: Vec<_> = missing.collect();
+    let extra = found.iter().filter(|s| !expected.contains(s));
+    let extra: Vec<_> = extra.collect();
+    Err(format!(
+        "{} solutions, not {}; missing {missing:?}, extra {extra:?}",
+        found.len(),
+        expected.len()
+    ))
+}
+
+// A random problem with up to 4 primary and 3 secondary items and 10
+// options, with colors and bounds other than 1..1 only if asked for.
+pub fn random_spec(
+    rng: &mut Rng,
+    colors: bool,
+    bounds: bool,
+) -> Spec<(Count, Data)> {
+    let np = 1 + rng.below(4) as Count;
+    let ns = if colors { rng.below(4) as Count } else { 0 };
+    let mut spec = Spec::new(np, ns);
+    if bounds {
+        for i in 0..np {
+            let u = rng.below(3) as Data;
+            let v = u.max(1) + rng.below(2) as Data;
+            spec.set_bounds(i, u, v);
+        }
+    }
+    for _ in 0..rng.below(11) {
+        let mut opt = Vec::new();
+        for i in 0..np + ns {
+            if rng.below(3) == 0 {
+                let c = if i < np { 0 } else { rng.below(3) as Data };
+                opt.push((i, c));
+            }
+        }
+        if !opt.is_empty() {
+            spec.add_option(opt);
+        }
+    }
+    spec
+}
+
+// Makes a failing problem smaller, one step at a time, for as long as it
+// still fails: dropping an option, an item, or an item from an option,
+// taking away bounds or colors.
+pub fn shrink(
+    mut spec: Spec<(Count, Data)>,
+    fails: impl Fn(&Spec<(Count, Data)>) -> bool,
+) -> Spec<(Count, Data)> {
+    assert!(fails(&spec));
+    while let Some(smaller) = smaller(&spec).into_iter().find(|s| fails(s)) {
+        spec = smaller;
+    }
+    spec
+}
+
+fn smaller(spec: &Spec<(Count, Data)>) -> Vec<Spec<(Count, Data)>> {
+    let np = spec.primary();
+    let ns = spec.secondary();
+    let bounds: Vec<(Data, Data)> = (0..np).map(|i| spec.bounds(i)).collect();
+    let options = spec.options();
+    let mut smaller = Vec::new();
+    for k in 0..options.len() {
+        let mut opts = options.to_vec();
+        opts.remove(k);
+        smaller.push(build(np, ns, &bounds, &opts));
+    }
+    for i in 0..np + ns {
+        let mut bounds = bounds.clone();
+        let (np, ns) = if i < np {
+            bounds.remove(i as usize);
+            (np - 1, ns)
+        } else {
+            (np, ns - 1)
+        };
+        let opts: Vec<Vec<(Count, Data)>> = options
+            .iter()
+            .map(|opt| {
+                let rest = opt.iter().filter(|&&(j, _)| j != i);
+                rest.map(|&(j, c)| (if j > i { j - 1 } else { j }, c))
+                    .collect()
+            })
+            .collect();
+        smaller.push(build(np, ns, &bounds, &opts));
+    }
+    for (k, opt) in options.iter().enumerate() {
+        for (e, &(_, c)) in opt.iter().enumerate() {
+            let mut opts = options.to_vec();
+            if opt.len() > 1 {
+                opts[k].remove(e);
+                smaller.push(build(np, ns, &bounds, &opts));
+                opts[k] = opt.clone();
+            }
+            if c != 0 {
+                opts[k][e].1 = 0;
+                smaller.push(build(np, ns, &bounds, &opts));
+            }
+        }
+    }
+    for i in 0..np as usize {
+        if bounds[i] != (1, 1) {
+            let mut bounds = bounds.clone();
+            bounds[i] = (1, 1);
+            smaller.push(build(np, ns, &bounds, options));
+        }
+    }
+    smaller
+}
+
+// Leaves out options with no items.
+fn build(
+    np: Count,
+    ns: Count,
+    bounds: &[(Data, Data)],
+    options: &[Vec<(Count, Data)>],
+) -> Spec<(Count, Data)> {
+    let mut spec = Spec::new(np, ns);
+    for (i, &(u, v)) in bounds.iter().enumerate() {
+        spec.set_bounds(i as Count, u, v);
+    }
+    for opt in options.iter().filter(|opt| !opt.is_empty()) {
+        spec.add_option(opt.iter().copied());
+    }
+    spec
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spec::taocp_xcc;
+
+    #[test]
+    fn test_reference() {
+        let spec = taocp_xcc();
+        assert_eq!(solutions(&spec), [[1, 3]]);
+
+        let mut spec = Spec::new(2, 1);
+        spec.set_bounds(0, 0, 2);
+        spec.add_option([(0, 0), (2, 1)]);
+        spec.add_option([(0, 0), (1, 0), (2, 1)]);
+        spec.add_option([(1, 0), (2, 0)]);
+        spec.add_option([(2, 1)]);
+        assert_eq!(solutions(&spec), [vec![0, 1], vec![1], vec![2]]);
+        assert_eq!(compare(&spec), Ok(()));
+    }
+
+    #[test]
+    fn test_differential() {
+        let mut rng = Rng::new(1);
+        let mut solved = 0;
+        for (colors, bounds) in
+            [(false, false), (true, false), (false, true), (true, true)]
+        {
+            for _ in 0..300 {
+                let spec = random_spec(&mut rng, colors, bounds);
+                solved += !solutions(&spec).is_empty() as usize;
+                if compare(&spec).is_err() {
+                    let spec = shrink(spec, |s| compare(s).is_err());
+                    let err = compare(&spec).unwrap_err();
+                    panic!("{err} for {spec:?}");
+                }
+            }
+        }
+        // Enough of them have solutions to compare.
+        assert!(solved > 300, "{solved}");
+    }
+
+    #[test]
+    fn test_shrink() {
+        // Fails whenever some two options share a secondary item with
+        // different colors.
+        let clash = |spec: &Spec<(Count, Data)>| {
+            let opts = spec.options();
+            opts.iter().any(|a| {
+                opts.iter().any(|b| {
+                    a.iter().any(|&(i, c)| {
+                        i >= spec.primary()
+                            && b.iter().any(|&(j, d)| i == j && c != d)
+                    })
+                })
+            })
+        };
+        let mut rng = Rng::new(2);
+        let spec = loop {
+            let spec = random_spec(&mut rng, true, true);
+            if clash(&spec) && spec.options().len() > 4 {
+                break spec;
+            }
+        };
+        let spec = shrink(spec, clash);
+        assert_eq!((spec.primary(), spec.secondary()), (0, 1));
+        assert!(!spec.has_bounds());
+        assert_eq!(spec.options().len(), 2);
+        assert!(spec.options().iter().all(|opt| opt.len() == 1));
+    }
+}
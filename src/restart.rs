@@ -0,0 +1,177 @@
+use crate::choose::Choose;
+use crate::items::Items;
+use crate::random::Rng;
+use crate::{ODance, Problem};
+
+// How many nodes each run of the search may visit before starting over.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Schedule {
+    // unit times the Luby sequence 1, 1, 2, 1, 1, 2, 4, 1, 1, 2, ...
+    Luby(u64),
+    // first, then each budget ratio times the last. Budgets must grow, or
+    // the search may never finish a run: first and unit must be positive,
+    // and ratio more than 1.
+    Geometric(u64, f64),
+}
+
+impl Schedule {
+    // The budget for run k, from 0.
+    pub fn budget(&self, k: u64) -> u64 {
+        match *self {
+            Schedule::Luby(unit) => unit.saturating_mul(luby(k + 1)),
+            Schedule::Geometric(first, ratio) => {
+                (first as f64 * ratio.powf(k as f64)).min(u64::MAX as f64)
+                    as u64
+            }
+        }
+    }
+}
+
+// The Luby sequence, from i = 1: 2^(j-1) if i = 2^j - 1, and otherwise
+// luby(i - 2^(j-1) + 1) for 2^(j-1) <= i < 2^j - 1.
+pub fn luby(mut i: u64) -> u64 {
+    assert!(i > 0);
+    loop {
+        let j = u64::BITS - i.leading_zeros();
+        if i == (1 << j) - 1 {
+            return 1 << (j - 1);
+        }
+        i -= (1 << (j - 1)) - 1;
+    }
+}
+
+// Looks for a solution with randomized choices, starting over with a new
+// seed whenever a run uses up its budget of nodes, since runs that go
+// astray early can take far longer than a fresh start.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Restarts {
+    schedule: Schedule,
+    rng: Rng,
+    runs: u64,
+    nodes: u64,
+}
+
+impl Restarts {
+    pub fn new(schedule: Schedule, seed: u64) -> Restarts {
+        match schedule {
+            Schedule::Luby(unit) => assert!(unit > 0, "zero unit"),
+            Schedule::Geometric(first, ratio) => {
+                assert!(first > 0, "zero first budget");
+                assert!(ratio > 1.0, "ratio not more than 1");
+            }
+        }
+        Restarts { schedule, rng: Rng::new(seed), runs: 0, nodes: 0 }
+    }
+
+    // Runs chooser(seed) on the problem, with a fresh seed for each run,
+    // until a run finds a solution, returning true, or finishes without
+    // one, returning false. After a solution the problem is left there,
+    // for find_options or to carry on with that run.
+    pub fn solve<I, O, C, F>(
+        &mut self,
+        problem: &mut Problem<I, O>,
+        mut chooser: F,
+    ) -> bool
+    where
+        I: Items,
+        O: ODance,
+        C: Choose<I>,
+        F: FnMut(u64) -> C,
+    {
+        self.runs = 0;
+        self.nodes = 0;
+        loop {
+            problem.reset();
+            let mut chooser = chooser(self.rng.next_u64());
+            problem.set_node_limit(self.schedule.budget(self.runs));
+            self.runs += 1;
+            let found = problem.next_solution(&mut chooser);
+            self.nodes += problem.nodes();
+            if found || !problem.is_paused() {
+                return found;
+            }
+        }
+    }
+
+    // The number of runs and the nodes they visited, for the last solve.
+    pub fn runs(&self) -> u64 {
+        self.runs
+    }
+
+    pub fn nodes(&self) -> u64 {
+        self.nodes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::choose::{self, MRVChooser};
+    use crate::queens;
+
+    #[test]
+    fn test_schedule() {
+        let seq: Vec<u64> = (1..=15).map(luby).collect();
+        assert_eq!(seq, [1, 1, 2, 1, 1, 2, 4, 1, 1, 2, 1, 1, 2, 4, 8]);
+        let luby = Schedule::Luby(10);
+        assert_eq!((luby.budget(0), luby.budget(6)), (10, 40));
+        let geometric = Schedule::Geometric(100, 1.5);
+        let budgets: Vec<u64> = (0..4).map(|k| geometric.budget(k)).collect();
+        assert_eq!(budgets, [100, 150, 225, 337]);
+        assert_eq!(Schedule::Geometric(1, 2.0).budget(100), u64::MAX);
+    }
+
+    #[test]
+    fn test_restarts() {
+        let random = |seed| {
+            MRVChooser::new(
+                choose::no_preference(),
+                choose::random_tiebreak(seed),
+            )
+        };
+        for schedule in [Schedule::Luby(4), Schedule::Geometric(2, 1.5)] {
+            let (items, opts) = queens::problem(12);
+            let mut problem = Problem::new(items, opts);
+            let mut restarts = Restarts::new(schedule, 1);
+            assert!(restarts.solve(&mut problem, random));
+            assert!(restarts.runs() > 1);
+            assert!(restarts.nodes() >= problem.nodes());
+            problem.find_options();
+            let board = queens::board(12, problem.get_options());
+            for (r, &c) in board.iter().enumerate() {
+                for (s, &d) in board[..r].iter().enumerate() {
+                    assert!(c != d && c.abs_diff(d) != r - s);
+                }
+            }
+            // The same seed takes the same runs.
+            let mut again = Restarts::new(schedule, 1);
+            problem.reset();
+            assert!(again.solve(&mut problem, random));
+            assert_eq!(again, restarts);
+
+            // Every run in this schedule finishes, the last with no
+            // solution.
+            let (items, opts) = queens::problem(3);
+            let mut problem = Problem::new(items, opts);
+            assert!(!restarts.solve(&mut problem, random));
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "zero unit")]
+    fn test_zero_unit() {
+        Restarts::new(Schedule::Luby(0), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "zero first budget")]
+    fn test_zero_first() {
+        Restarts::new(Schedule::Geometric(0, 2.0), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "ratio not more than 1")]
+    fn test_ratio() {
+        Restarts::new(Schedule::Geometric(10, 1.0), 1);
+    }
+}
@@ -0,0 +1,339 @@
+#![allow(clippy::unnecessary_cast)]
+use crate::choose::{self, KnuthTiebreak, MRVChooser};
+use crate::random::Rng;
+use crate::spec::Spec;
+use crate::{Count, Data, Problem};
+
+// Draws random solutions of a problem, as the sorted indexes of their
+// options. Use Spec::colored for a problem without colors.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sampler {
+    spec: Spec<(Count, Data)>,
+    rng: Rng,
+}
+
+// What is left of the problem once some options are chosen or ruled out,
+// with the original index of each option that is left.
+#[derive(Clone, Debug, PartialEq)]
+struct Rest {
+    spec: Spec<(Count, Data)>,
+    index: Vec<usize>,
+}
+
+impl Sampler {
+    pub fn new(spec: Spec<(Count, Data)>, seed: u64) -> Sampler {
+        Sampler { spec, rng: Rng::new(seed) }
+    }
+
+    pub fn count(&self) -> u64 {
+        count(&self.spec)
+    }
+
+    // A solution drawn uniformly, or None if there is none. Each step
+    // takes an item that must be covered exactly once and picks one of its
+    // options with probability proportional to the number of solutions
+    // that go on from it, counted by searching what is left. (Items with
+    // other bounds take their options one at a time, in or out.) This is
+    // exact, but only feasible when those counts are.
+    pub fn sample(&mut self) -> Option<Vec<usize>> {
+        let mut rest = Rest::new(&self.spec);
+        let mut total = count(&rest.spec);
+        if total == 0 {
+            return None;
+        }
+        let mut chosen = Vec::new();
+        while !rest.spec.options().is_empty() {
+            let r = self.rng.below(total);
+            if let Some(i) = rest.exact_item() {
+                let opts: Vec<usize> = rest.options_of(i).collect();
+                let mut seen = 0;
+                for k in opts {
+                    let next = rest.choose(k);
+                    let c = count(&next.spec);
+                    seen += c;
+                    if r < seen {
+                        chosen.push(rest.index[k]);
+                        (rest, total) = (next, c);
+                        break;
+                    }
+                }
+                assert!(r < seen, "counts do not add up");
+            } else {
+                let next = rest.choose(0);
+                let c = count(&next.spec);
+                if r < c {
+                    chosen.push(rest.index[0]);
+                    (rest, total) = (next, c);
+                } else {
+                    (rest, total) = (rest.rule_out(0), total - c);
+                }
+            }
+        }
+        chosen.sort();
+        Some(chosen)
+    }
+
+    // A cheap alternative to sample, which does no counting: each step
+    // takes the item with fewest options among those still needing to be
+    // covered and picks one of its options uniformly, starting over at a
+    // dead end, up to tries times in all. Once every item has been covered
+    // often enough, it stops or adds one of the options left, all equally
+    // likely. The result is a solution, but NOT a uniform one: solutions
+    // reached through fewer or narrower choices come up more often.
+    pub fn random_walk(&mut self, tries: u64) -> Option<Vec<usize>> {
+        'walk: for _ in 0..tries {
+            let mut rest = Rest::new(&self.spec);
+            let mut chosen = Vec::new();
+            loop {
+                let needed = (0..rest.spec.primary())
+                    .filter(|&i| rest.spec.bounds(i).0 > 0)
+                    .min_by_key(|&i| rest.options_of(i).count());
+                let k = match needed {
+                    Some(i) => {
+                        let opts: Vec<usize> = rest.options_of(i).collect();
+                        if opts.is_empty() {
+                            continue 'walk;
+                        }
+                        opts[self.rng.below(opts.len() as u64) as usize]
+                    }
+                    None => {
+                        let n = rest.spec.options().len();
+                        let k = self.rng.below(n as u64 + 1) as usize;
+                        if k == n {
+                            break;
+                        }
+                        k
+                    }
+                };
+                chosen.push(rest.index[k]);
+                rest = rest.choose(k);
+            }
+            chosen.sort();
+            return Some(chosen);
+        }
+        None
+    }
+}
+
+fn count(spec: &Spec<(Count, Data)>) -> u64 {
+    let mut chooser = MRVChooser::new(choose::no_preference(), KnuthTiebreak());
+    let mut problem = Problem::new(spec.items_m(), spec.onodes_c());
+    problem.count_solutions(&mut chooser)
+}
+
+impl Rest {
+    fn new(spec: &Spec<(Count, Data)>) -> Rest {
+        let all = Rest {
+            spec: spec.clone(),
+            index: (0..spec.options().len()).collect(),
+        };
+        // Options with no primary item are never chosen.
+        all.keep(|_, opt| opt.iter().any(|&(i, _)| i < spec.primary()))
+    }
+
+    // The primary item with fewest options among those to be covered
+    // exactly once.
+    fn exact_item(&self) -> Option<Count> {
+        (0..self.spec.primary())
+            .filter(|&i| self.spec.bounds(i) == (1, 1))
+            .min_by_key(|&i| self.options_of(i).count())
+    }
+
+    fn options_of(&self, i: Count) -> impl Iterator<Item = usize> + '_ {
+        let opts = self.spec.options().iter().enumerate();
+        opts.filter(move |(_, opt)| opt.iter().any(|&(j, _)| j == i))
+            .map(|(k, _)| k)
+    }
+
+    fn rule_out(&self, k: usize) -> Rest {
+        self.keep(|j, _| j != k)
+    }
+
+    // The options left once option k is chosen: the primary items it
+    // covers as often as they may be covered are gone, along with their
+    // options, and so are its secondary items, along with the options
+    // that give them other colors.
+    fn choose(&self, k: usize) -> Rest {
+        let spec = &self.spec;
+        let np = spec.primary();
+        let chosen = &spec.options()[k];
+        let mut bounds: Vec<(Data, Data)> =
+            (0..np).map(|i| spec.bounds(i)).collect();
+        let mut colors = vec![None; (np + spec.secondary()) as usize];
+        for &(i, c) in chosen {
+            if i < np {
+                let (u, v) = bounds[i as usize];
+                bounds[i as usize] = ((u - 1).max(0), v - 1);
+            } else {
+                colors[i as usize] = Some(c);
+            }
+        }
+        let gone = |i: Count| {
+            if i < np {
+                bounds[i as usize].1 == 0
+            } else {
+                colors[i as usize].is_some()
+            }
+        };
+        let fits = |j: usize, opt: &[(Count, Data)]| {
+            j != k
+                && opt.iter().all(|&(i, c)| match colors[i as usize] {
+                    _ if i < np => !gone(i),
+                    None => true,
+                    Some(d) => c != 0 && c == d,
+                })
+        };
+
+        // Numbers the items that are left, primary items first.
+        let mut number = vec![None; colors.len()];
+        let (mut primary, mut secondary) = (0, 0);
+        for i in 0..colors.len() as Count {
+            if gone(i) {
+                continue;
+            }
+            if i < np {
+                number[i as usize] = Some(primary);
+                primary += 1;
+            } else {
+                number[i as usize] = Some(secondary);
+                secondary += 1;
+            }
+        }
+        let mut rest = Spec::new(primary, secondary);
+        for i in 0..np {
+            if let Some(n) = number[i as usize] {
+                let (u, v) = bounds[i as usize];
+                rest.set_bounds(n, u, v);
+            }
+        }
+        let mut index = Vec::new();
+        for (j, opt) in spec.options().iter().enumerate() {
+            if !fits(j, opt) {
+                continue;
+            }
+            let opt: Vec<(Count, Data)> = opt
+                .iter()
+                .filter_map(|&(i, c)| match number[i as usize] {
+                    Some(n) if i < np => Some((n, c)),
+                    Some(n) => Some((primary + n, c)),
+                    None => None,
+                })
+                .collect();
+            if opt.iter().any(|&(i, _)| i < primary) {
+                rest.add_option(opt);
+                index.push(self.index[j]);
+            }
+        }
+        Rest { spec: rest, index }
+    }
+
+    fn keep(&self, keep: impl Fn(usize, &[(Count, Data)]) -> bool) -> Rest {
+        let spec = &self.spec;
+        let mut rest = Spec::new(spec.primary(), spec.secondary());
+        for i in 0..spec.primary() {
+            let (u, v) = spec.bounds(i);
+            rest.set_bounds(i, u, v);
+        }
+        let mut index = Vec::new();
+        for (j, opt) in spec.options().iter().enumerate() {
+            if keep(j, opt) {
+                rest.add_option(opt.iter().copied());
+                index.push(self.index[j]);
+            }
+        }
+        Rest { spec: rest, index }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::partition::SetPartition;
+    use crate::polyomino::rectangle;
+    use crate::spec::taocp_xcc;
+    use crate::{dominoes, queens};
+
+    // Draws 100 samples per solution, checking that each is one of the
+    // solutions and that they come up about equally often.
+    fn verify_uniform(spec: Spec<(Count, Data)>, solutions: &[Vec<usize>]) {
+        let mut sampler = Sampler::new(spec, 1);
+        assert_eq!(sampler.count(), solutions.len() as u64);
+        let mut counts = vec![0; solutions.len()];
+        for _ in 0..100 * solutions.len() {
+            let s = sampler.sample().unwrap();
+            let k = solutions.iter().position(|t| *t == s);
+            counts[k.expect("not a solution")] += 1;
+        }
+        assert!(counts.iter().all(|&c| (60..140).contains(&c)), "{counts:?}");
+    }
+
+    #[test]
+    fn test_sample() {
+        let mut chooser =
+            MRVChooser::new(choose::no_preference(), choose::first_wins());
+        let mut solutions = Vec::new();
+        let (items, opts) = queens::problem(6);
+        let mut problem = Problem::new(items, opts);
+        while problem.next_solution(&mut chooser) {
+            problem.find_options();
+            let mut s: Vec<usize> =
+                problem.get_options().iter().map(|&k| k as usize).collect();
+            s.sort();
+            solutions.push(s);
+        }
+        verify_uniform(queens::spec(6).colored(), &solutions);
+
+        let spec = dominoes::tiling(&rectangle(4, 3)).colored();
+        let mut sampler = Sampler::new(spec.clone(), 2);
+        let solutions: Vec<Vec<usize>> =
+            (0..500).filter_map(|_| sampler.sample()).collect();
+        let mut distinct = solutions.clone();
+        distinct.sort();
+        distinct.dedup();
+        assert_eq!(distinct.len(), 11);
+        verify_uniform(spec, &distinct);
+
+        let mut partition = SetPartition::new(4);
+        for bits in [0b11, 0b1100, 0b1, 0b10, 0b110, 0b1000, 0b1111] {
+            partition.add_bits(bits);
+        }
+        partition.set_cover(1, 1, 2);
+        partition.set_cover(3, 0, 1);
+        let solutions: Vec<Vec<usize>> = partition.solutions().collect();
+        assert!(solutions.len() > 5);
+        verify_uniform(partition.spec().colored(), &solutions);
+
+        let spec = taocp_xcc();
+        verify_uniform(spec, &[vec![1, 3]]);
+
+        let mut sampler = Sampler::new(queens::spec(3).colored(), 1);
+        assert_eq!(sampler.sample(), None);
+        assert_eq!(sampler.random_walk(100), None);
+    }
+
+    #[test]
+    fn test_random_walk() {
+        let mut partition = SetPartition::new(4);
+        for bits in [0b11, 0b1100, 0b1, 0b10, 0b110, 0b1000, 0b1111] {
+            partition.add_bits(bits);
+        }
+        partition.set_cover(1, 1, 2);
+        partition.set_cover(3, 0, 1);
+        let mut solutions: Vec<Vec<usize>> = partition.solutions().collect();
+        solutions.sort();
+        let mut sampler = Sampler::new(partition.spec().colored(), 3);
+        let mut seen = Vec::new();
+        for _ in 0..1000 {
+            let s = sampler.random_walk(10).unwrap();
+            assert!(solutions.contains(&s), "{s:?} is not a solution");
+            seen.push(s);
+        }
+        seen.sort();
+        seen.dedup();
+        assert_eq!(seen, solutions);
+
+        let mut sampler = Sampler::new(queens::spec(8).colored(), 1);
+        let board = sampler.random_walk(1000).unwrap();
+        assert_eq!(board.len(), 8);
+    }
+}
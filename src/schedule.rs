@@ -0,0 +1,210 @@
+#![allow(clippy::unnecessary_cast)]
+use crate::choose::{self, KnuthTiebreak, MRVChooser, NoPreference};
+use crate::links::{INodesM, ONodes};
+use crate::spec::Spec;
+use crate::{Count, Data, Problem};
+
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub struct Game {
+    pub teams: (usize, usize),
+    pub venue: usize,
+}
+
+// A round robin: every pair of teams meets once, in one of the rounds and
+// at one of the venues, and no team plays twice in a round.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Tournament {
+    teams: usize,
+    rounds: usize,
+    per_round: (usize, usize),
+    venues: Vec<usize>,
+}
+
+impl Tournament {
+    // By default there is one venue, big enough for every game of a round.
+    pub fn new(teams: usize, rounds: usize) -> Tournament {
+        assert!(teams > 1 && rounds > 0);
+        Tournament {
+            teams,
+            rounds,
+            per_round: (0, teams / 2),
+            venues: vec![teams / 2],
+        }
+    }
+
+    // Each round has between lo and hi games.
+    pub fn set_games_per_round(&mut self, lo: usize, hi: usize) {
+        assert!(lo <= hi && hi > 0, "invalid bounds");
+        self.per_round = (lo, hi);
+    }
+
+    // The number of games each venue can hold in one round.
+    pub fn set_venues(&mut self, capacities: &[usize]) {
+        assert!(capacities.iter().all(|&c| c > 0), "empty venue");
+        self.venues = capacities.to_vec();
+    }
+
+    pub fn games(&self) -> Vec<(usize, usize)> {
+        let n = self.teams;
+        (0..n)
+            .flat_map(|a| (a + 1..n).map(move |b| (a, b)))
+            .collect()
+    }
+
+    // Items are the games, the rounds and the (venue, round) pairs, all
+    // primary, then (team, round) pairs as secondary items. Option k plays
+    // a game in some round at some venue.
+    pub fn spec(&self) -> Spec<Count> {
+        self.build().0
+    }
+
+    // The games of each round, in order.
+    pub fn solutions(&self) -> Solutions {
+        let (spec, slots) = self.build();
+        Solutions {
+            problem: Problem::new(spec.items_m(), spec.onodes()),
+            chooser: MRVChooser::new(choose::no_preference(), KnuthTiebreak()),
+            slots,
+            rounds: self.rounds,
+            done: false,
+        }
+    }
+
+    // Also returns the round and game played by each option.
+    fn build(&self) -> (Spec<Count>, Vec<(usize, Game)>) {
+        let games = self.games();
+        let (r, v) = (self.rounds, self.venues.len());
+        let round0 = games.len();
+        let venue0 = round0 + r;
+        let np = venue0 + v * r;
+        let team = |t: usize, round: usize| (np + t * r + round) as Count;
+
+        let mut spec = Spec::new(np as Count, (self.teams * r) as Count);
+        let (lo, hi) = self.per_round;
+        for round in 0..r {
+            spec.set_bounds((round0 + round) as Count, lo as Data, hi as Data);
+            for (s, &cap) in self.venues.iter().enumerate() {
+                let i = venue0 + s * r + round;
+                spec.set_bounds(i as Count, 0, cap as Data);
+            }
+        }
+        let mut slots = Vec::new();
+        for (g, &(a, b)) in games.iter().enumerate() {
+            for round in 0..r {
+                for venue in 0..v {
+                    spec.add_option([
+                        g as Count,
+                        (round0 + round) as Count,
+                        (venue0 + venue * r + round) as Count,
+                        team(a, round),
+                        team(b, round),
+                    ]);
+                    slots.push((round, Game { teams: (a, b), venue }));
+                }
+            }
+        }
+        (spec, slots)
+    }
+}
+
+pub struct Solutions {
+    problem: Problem<INodesM, ONodes>,
+    chooser: MRVChooser<NoPreference, KnuthTiebreak>,
+    slots: Vec<(usize, Game)>,
+    rounds: usize,
+    done: bool,
+}
+
+impl Iterator for Solutions {
+    type Item = Vec<Vec<Game>>;
+
+    fn next(&mut self) -> Option<Vec<Vec<Game>>> {
+        if self.done || !self.problem.next_solution(&mut self.chooser) {
+            self.done = true;
+            return None;
+        }
+        self.problem.find_options();
+        let mut timetable = vec![Vec::new(); self.rounds];
+        for &k in self.problem.get_options() {
+            let (round, game) = self.slots[k as usize];
+            timetable[round].push(game);
+        }
+        for games in &mut timetable {
+            games.sort();
+        }
+        Some(timetable)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_valid(t: &Tournament, timetable: &[Vec<Game>]) -> bool {
+        let mut played: Vec<(usize, usize)> =
+            timetable.iter().flatten().map(|g| g.teams).collect();
+        played.sort();
+        let rounds = timetable.iter().all(|games| {
+            let mut teams: Vec<usize> =
+                games.iter().flat_map(|g| [g.teams.0, g.teams.1]).collect();
+            teams.sort();
+            teams.dedup();
+            let venues = t.venues.iter().enumerate().all(|(s, &cap)| {
+                games.iter().filter(|g| g.venue == s).count() <= cap
+            });
+            teams.len() == 2 * games.len()
+                && (t.per_round.0..=t.per_round.1).contains(&games.len())
+                && venues
+        });
+        played == t.games() && rounds
+    }
+
+    #[test]
+    fn test_round_robin() {
+        // K4 has one 1-factorization and K6 has six, each of which can be
+        // played in any order of rounds.
+        let mut t = Tournament::new(4, 3);
+        t.set_games_per_round(2, 2);
+        assert_eq!(t.solutions().count(), 6);
+        t.set_venues(&[1, 1]);
+        assert_eq!(t.solutions().count(), 6 * 8);
+
+        let mut t = Tournament::new(6, 5);
+        t.set_games_per_round(3, 3);
+        let mut count = 0;
+        for timetable in t.solutions() {
+            assert!(is_valid(&t, &timetable));
+            count += 1;
+        }
+        assert_eq!(count, 6 * 120);
+    }
+
+    #[test]
+    fn test_bounds() {
+        let mut t = Tournament::new(4, 4);
+        t.set_games_per_round(1, 2);
+        t.set_venues(&[1, 1]);
+
+        // Every way of giving each game a round and a venue.
+        let mut expected = Vec::new();
+        let games = t.games();
+        for code in 0..8usize.pow(games.len() as u32) {
+            let mut timetable = vec![Vec::new(); 4];
+            for (i, &teams) in games.iter().enumerate() {
+                let slot = code / 8usize.pow(i as u32) % 8;
+                timetable[slot / 2].push(Game { teams, venue: slot % 2 });
+            }
+            for games in &mut timetable {
+                games.sort();
+            }
+            if is_valid(&t, &timetable) {
+                expected.push(timetable);
+            }
+        }
+        let mut found: Vec<Vec<Vec<Game>>> = t.solutions().collect();
+        found.sort();
+        expected.sort();
+        assert!(!expected.is_empty());
+        assert_eq!(found, expected);
+    }
+}
@@ -0,0 +1,217 @@
+#![allow(clippy::unnecessary_cast)]
+use crate::arrays::{OArrays, OArraysC};
+use crate::links::{
+    INode, INodeM, INodes, INodesM, ONode, ONodeC, ONodes, ONodesC,
+};
+use crate::{Count, Data, OptSpec};
+
+// Items and options of a problem, before they are linked into nodes.
+// Items are numbered from 0, primary items first.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Spec<S> {
+    primary: Count,
+    secondary: Count,
+    bounds: Vec<(Data, Data)>,
+    options: Vec<Vec<S>>,
+}
+
+impl<S: OptSpec + Copy> Spec<S> {
+    pub fn new(primary: Count, secondary: Count) -> Spec<S> {
+        Spec {
+            primary,
+            secondary,
+            bounds: vec![(1, 1); primary as usize],
+            options: Vec::new(),
+        }
+    }
+
+    pub fn primary(&self) -> Count {
+        self.primary
+    }
+
+    pub fn secondary(&self) -> Count {
+        self.secondary
+    }
+
+    pub fn bounds(&self, i: Count) -> (Data, Data) {
+        self.bounds[i as usize]
+    }
+
+    // Primary item i must be covered between u and v times.
+    pub fn set_bounds(&mut self, i: Count, u: Data, v: Data) {
+        assert!(i < self.primary, "only primary items have bounds");
+        assert!(0 <= u && u <= v && 0 < v, "invalid bounds");
+        self.bounds[i as usize] = (u, v);
+    }
+
+    pub fn has_bounds(&self) -> bool {
+        self.bounds.iter().any(|&b| b != (1, 1))
+    }
+
+    pub fn add_option(&mut self, opt: impl IntoIterator<Item = S>) {
+        let opt: Vec<S> = opt.into_iter().collect();
+        let n = self.primary + self.secondary;
+        for (a, s) in opt.iter().enumerate() {
+            let i = s.get_item();
+            assert!(i < n, "item out of range");
+            assert!(
+                opt[..a].iter().all(|t| t.get_item() != i),
+                "item repeated in an option"
+            );
+        }
+        self.options.push(opt);
+    }
+
+    pub fn options(&self) -> &[Vec<S>] {
+        &self.options
+    }
+
+    // The number of options.
+    pub fn size(&self) -> Count {
+        self.options.len() as Count
+    }
+
+    // The number of option nodes.
+    pub fn nodes(&self) -> Count {
+        let mut lens = self.options.iter().map(|o| o.len());
+        let nodes = lens.try_fold(0, Count::checked_add);
+        nodes.expect("too many nodes")
+    }
+
+    pub fn items(&self) -> INodes {
+        assert!(!self.has_bounds(), "bounds need INodesM");
+        INode::make_nodes(self.primary, self.secondary)
+    }
+
+    pub fn items_m(&self) -> INodesM {
+        INodeM::make_nodes(
+            self.primary,
+            self.secondary,
+            self.bounds.iter().copied(),
+        )
+    }
+
+    fn opt_spec(&self) -> impl Iterator<Item = impl Iterator<Item = S>> {
+        self.options.iter().map(|o| o.iter().copied())
+    }
+}
+
+impl Spec<Count> {
+    pub fn onodes(&self) -> ONodes {
+        ONode::make_nodes(
+            self.primary,
+            self.secondary,
+            self.size(),
+            self.nodes(),
+            self.opt_spec(),
+        )
+    }
+
+    pub fn oarrays(&self) -> OArrays {
+        OArrays::make_nodes(
+            self.primary,
+            self.secondary,
+            self.size(),
+            self.nodes(),
+            self.opt_spec(),
+        )
+    }
+
+    pub fn colored(&self) -> Spec<(Count, Data)> {
+        Spec {
+            primary: self.primary,
+            secondary: self.secondary,
+            bounds: self.bounds.clone(),
+            options: self
+                .options
+                .iter()
+                .map(|o| o.iter().map(|&i| (i, 0)).collect())
+                .collect(),
+        }
+    }
+}
+
+impl Spec<(Count, Data)> {
+    // The same problem without colors, if no option uses any.
+    pub fn uncolored(&self) -> Option<Spec<Count>> {
+        let options = self
+            .options
+            .iter()
+            .map(|o| o.iter().map(|&(i, c)| (c == 0).then_some(i)).collect())
+            .collect::<Option<_>>()?;
+        Some(Spec {
+            primary: self.primary,
+            secondary: self.secondary,
+            bounds: self.bounds.clone(),
+            options,
+        })
+    }
+
+    pub fn onodes_c(&self) -> ONodesC {
+        ONodeC::make_nodes(
+            self.primary,
+            self.secondary,
+            self.size(),
+            self.nodes(),
+            self.opt_spec(),
+        )
+    }
+
+    pub fn oarrays_c(&self) -> OArraysC {
+        OArraysC::make_nodes(
+            self.primary,
+            self.secondary,
+            self.size(),
+            self.nodes(),
+            self.opt_spec(),
+        )
+    }
+}
+
+// The example of TAocp Vol. 4B p. 89, with primary items p, q, r and
+// secondary items x, y. Its only solution is options 1 and 3.
+#[cfg(test)]
+pub fn taocp_xcc() -> Spec<(Count, Data)> {
+    let mut spec = Spec::new(3, 2);
+    spec.add_option([(0, 0), (1, 0), (3, 0), (4, 1)]);
+    spec.add_option([(0, 0), (2, 0), (3, 1), (4, 0)]);
+    spec.add_option([(0, 0), (3, 2)]);
+    spec.add_option([(1, 0), (3, 1)]);
+    spec.add_option([(2, 0), (4, 2)]);
+    spec
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spec() {
+        let spec = taocp_xcc();
+        let opt_spec = spec.options().to_vec();
+        assert_eq!((spec.size(), spec.nodes()), (5, 14));
+        assert_eq!(spec.items(), INode::make_nodes(3, 2));
+        let arrays = OArraysC::make_nodes(3, 2, 5, 14, opt_spec.clone());
+        assert_eq!(spec.oarrays_c(), arrays);
+        assert_eq!(spec.onodes_c(), ONodeC::make_nodes(3, 2, 5, 14, opt_spec));
+        assert!(spec.uncolored().is_none());
+
+        let mut spec = Spec::new(2, 0);
+        spec.add_option([0, 1]);
+        spec.set_bounds(1, 0, 2);
+        assert!(spec.has_bounds());
+        assert_eq!(spec.items_m(), INodeM::make_nodes(2, 0, [(1, 1), (0, 2)]));
+        assert_eq!(
+            spec.colored().onodes_c(),
+            ONodeC::make_nodes(2, 0, 1, 2, [[(0, 0), (1, 0)]])
+        );
+        assert_eq!(spec.colored().uncolored(), Some(spec));
+    }
+
+    #[test]
+    #[should_panic(expected = "item repeated in an option")]
+    fn test_repeated() {
+        let mut spec = Spec::new(2, 1);
+        spec.add_option([(0, 0), (2, 1), (2, 1)]);
+    }
+}
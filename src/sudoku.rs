@@ -0,0 +1,359 @@
+#![allow(clippy::unnecessary_cast)]
+use crate::choose::{self, FirstWins, MRVChooser, NoPreference};
+use crate::links::{INodes, ONodesC};
+use crate::spec::Spec;
+use crate::{Count, Data, Problem};
+
+// The (cell, digit) placed by an option.
+type Move = Option<(usize, usize)>;
+
+// A killer cage: its cells hold distinct digits adding up to sum.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Cage {
+    pub cells: Vec<usize>,
+    pub sum: usize,
+}
+
+// An n^2 x n^2 grid; givens are stored row by row, 0 for an empty cell.
+// Regions default to the n x n boxes.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Sudoku {
+    n: usize,
+    givens: Vec<usize>,
+    regions: Vec<usize>,
+    diagonals: bool,
+    cages: Vec<Cage>,
+}
+
+impl Sudoku {
+    pub fn new(n: usize, givens: &[usize]) -> Sudoku {
+        let size = n * n;
+        assert!(n > 0 && givens.len() == size * size, "wrong grid size");
+        assert!(givens.iter().all(|&d| d <= size), "digit out of range");
+        let regions = (0..size * size)
+            .map(|cell| {
+                let (r, c) = (cell / size, cell % size);
+                (r / n) * n + c / n
+            })
+            .collect();
+        Sudoku {
+            n,
+            givens: givens.to_vec(),
+            regions,
+            diagonals: false,
+            cages: Vec::new(),
+        }
+    }
+
+    // Digits are 1-9 then A-Z; '.' and '0' are empty cells. Whitespace is
+    // ignored.
+    pub fn parse(n: usize, s: &str) -> Option<Sudoku> {
+        let mut givens = Vec::new();
+        for ch in s.chars().filter(|ch| !ch.is_whitespace()) {
+            let d = match ch {
+                '.' => 0,
+                _ => ch.to_digit(36)? as usize,
+            };
+            if d > n * n {
+                return None;
+            }
+            givens.push(d);
+        }
+        (givens.len() == n * n * n * n).then(|| Sudoku::new(n, &givens))
+    }
+
+    pub fn size(&self) -> usize {
+        self.n * self.n
+    }
+
+    // Replaces the boxes by arbitrary regions (jigsaw Sudoku). Each of the
+    // n^2 region numbers must be used for exactly n^2 cells.
+    pub fn set_regions(&mut self, regions: &[usize]) {
+        let size = self.size();
+        assert!(regions.len() == size * size, "wrong grid size");
+        let mut counts = vec![0; size];
+        for &g in regions {
+            assert!(g < size, "region out of range");
+            counts[g] += 1;
+        }
+        assert!(counts.iter().all(|&k| k == size), "uneven regions");
+        self.regions = regions.to_vec();
+    }
+
+    // Both main diagonals must also hold every digit.
+    pub fn set_diagonals(&mut self, diagonals: bool) {
+        self.diagonals = diagonals;
+    }
+
+    pub fn add_cage(&mut self, cells: &[usize], sum: usize) {
+        let size = self.size();
+        assert!(!cells.is_empty() && cells.len() <= size, "wrong cage size");
+        assert!(cells.iter().all(|&c| c < size * size), "cell out of range");
+        self.cages.push(Cage { cells: cells.to_vec(), sum });
+    }
+
+    pub fn cages(&self) -> &[Cage] {
+        &self.cages
+    }
+
+    pub fn spec(&self) -> Spec<(Count, Data)> {
+        self.build().0
+    }
+
+    // Filled grids, row by row.
+    pub fn solutions(&self) -> Solutions {
+        let (spec, moves) = self.build();
+        Solutions {
+            problem: Problem::new(spec.items(), spec.onodes_c()),
+            chooser: MRVChooser::new(
+                choose::no_preference(),
+                choose::first_wins(),
+            ),
+            moves,
+            cells: self.size() * self.size(),
+            done: false,
+        }
+    }
+
+    // Stops searching after the second solution.
+    pub fn has_unique_solution(&self) -> bool {
+        self.solutions().take(2).count() == 1
+    }
+
+    pub fn render(&self, grid: &[usize]) -> String {
+        let mut s = String::new();
+        for row in grid.chunks(self.size()) {
+            for &d in row {
+                s.push(match char::from_digit(d as u32, 36) {
+                    Some(ch) if d != 0 => ch.to_ascii_uppercase(),
+                    _ => '.',
+                });
+            }
+            s.push('\n');
+        }
+        s
+    }
+
+    // Items are cells, then (row, digit), (column, digit) and
+    // (region, digit) pairs, the digits of the two diagonals and one item
+    // per cage, all primary. Each cage also has two secondary items per
+    // digit: an uncolored one that keeps the cage's digits distinct, and
+    // one colored by whether the digit is in the set chosen for the cage.
+    //
+    // Also returns the (cell, digit) placed by each option; cage options
+    // place nothing.
+    fn build(&self) -> (Spec<(Count, Data)>, Vec<Move>) {
+        const IN_CAGE: Data = 1;
+        const NOT_IN_CAGE: Data = 2;
+
+        let size = self.size();
+        let cells = size * size;
+        let diag = 4 * cells;
+        let cage0 = diag + if self.diagonals { 2 * size } else { 0 };
+        let np = cage0 + self.cages.len();
+        let ns = 2 * self.cages.len() * size;
+        let distinct = |k: usize, d: usize| (np + 2 * (k * size + d)) as Count;
+        let chosen = |k: usize, d: usize| distinct(k, d) + 1;
+
+        let mut in_cage = vec![None; cells];
+        for (k, cage) in self.cages.iter().enumerate() {
+            for &cell in &cage.cells {
+                assert!(in_cage[cell].is_none(), "cages overlap");
+                in_cage[cell] = Some(k);
+            }
+        }
+
+        let mut spec = Spec::new(np as Count, ns as Count);
+        let mut moves = Vec::new();
+        for (cell, &cage) in in_cage.iter().enumerate() {
+            let (r, c) = (cell / size, cell % size);
+            let g = self.regions[cell];
+            let given = self.givens[cell];
+            for d in 0..size {
+                if given != 0 && given != d + 1 {
+                    continue;
+                }
+                let mut opt = vec![
+                    (cell as Count, 0),
+                    ((cells + r * size + d) as Count, 0),
+                    ((2 * cells + c * size + d) as Count, 0),
+                    ((3 * cells + g * size + d) as Count, 0),
+                ];
+                if self.diagonals && r == c {
+                    opt.push(((diag + d) as Count, 0));
+                }
+                if self.diagonals && r + c == size - 1 {
+                    opt.push(((diag + size + d) as Count, 0));
+                }
+                if let Some(k) = cage {
+                    opt.push((distinct(k, d), 0));
+                    opt.push((chosen(k, d), IN_CAGE));
+                }
+                spec.add_option(opt);
+                moves.push(Some((cell, d + 1)));
+            }
+        }
+        for (k, cage) in self.cages.iter().enumerate() {
+            for set in digit_sets(size, cage.cells.len(), cage.sum) {
+                let mut opt = vec![((cage0 + k) as Count, 0)];
+                for d in 0..size {
+                    if set & (1 << d) == 0 {
+                        opt.push((chosen(k, d), NOT_IN_CAGE));
+                    }
+                }
+                spec.add_option(opt);
+                moves.push(None);
+            }
+        }
+        (spec, moves)
+    }
+}
+
+// Sets of k distinct digits from 1..=size adding up to sum, as bit masks
+// with bit d - 1 standing for digit d.
+pub(crate) fn digit_sets(size: usize, k: usize, sum: usize) -> Vec<u64> {
+    assert!(size <= 64);
+    let mut sets = Vec::new();
+    let mut stack = vec![(0u64, 0, 0, 0)];
+    while let Some((set, next, len, total)) = stack.pop() {
+        if len == k {
+            if total == sum {
+                sets.push(set);
+            }
+            continue;
+        }
+        for d in next..size {
+            if total + d + 1 > sum {
+                break;
+            }
+            stack.push((set | (1 << d), d + 1, len + 1, total + d + 1));
+        }
+    }
+    sets
+}
+
+pub struct Solutions {
+    problem: Problem<INodes, ONodesC>,
+    chooser: MRVChooser<NoPreference, FirstWins<INodes>>,
+    moves: Vec<Move>,
+    cells: usize,
+    done: bool,
+}
+
+impl Iterator for Solutions {
+    type Item = Vec<usize>;
+
+    fn next(&mut self) -> Option<Vec<usize>> {
+        if self.done || !self.problem.next_solution(&mut self.chooser) {
+            self.done = true;
+            return None;
+        }
+        self.problem.find_options();
+        let mut grid = vec![0; self.cells];
+        for &k in self.problem.get_options() {
+            if let Some((cell, d)) = self.moves[k as usize] {
+                grid[cell] = d;
+            }
+        }
+        Some(grid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_valid(sudoku: &Sudoku, grid: &[usize]) -> bool {
+        let size = sudoku.size();
+        let distinct = |cells: &mut dyn Iterator<Item = usize>| {
+            let mut seen = 0u64;
+            for cell in cells {
+                if seen & (1 << grid[cell]) != 0 {
+                    return false;
+                }
+                seen |= 1 << grid[cell];
+            }
+            true
+        };
+        let givens = sudoku
+            .givens
+            .iter()
+            .zip(grid)
+            .all(|(&g, &d)| g == 0 || g == d);
+        let units = (0..size).all(|i| {
+            distinct(&mut (0..size).map(|j| i * size + j))
+                && distinct(&mut (0..size).map(|j| j * size + i))
+                && distinct(
+                    &mut (0..size * size).filter(|&c| sudoku.regions[c] == i),
+                )
+        });
+        let diagonals = !sudoku.diagonals
+            || (distinct(&mut (0..size).map(|i| i * size + i))
+                && distinct(&mut (0..size).map(|i| i * size + size - 1 - i)));
+        let cages = sudoku.cages.iter().all(|cage| {
+            let sum: usize = cage.cells.iter().map(|&c| grid[c]).sum();
+            distinct(&mut cage.cells.iter().copied()) && sum == cage.sum
+        });
+        givens && units && diagonals && cages
+    }
+
+    fn count(sudoku: &Sudoku) -> usize {
+        let mut count = 0;
+        for grid in sudoku.solutions() {
+            assert!(is_valid(sudoku, &grid), "invalid grid");
+            count += 1;
+        }
+        count
+    }
+
+    #[test]
+    fn test_sudoku() {
+        let sudoku = Sudoku::parse(
+            3,
+            "53..7.... 6..195... .98....6. 8...6...3 4..8.3..1 \
+             7...2...6 .6....28. ...419..5 ....8..79",
+        )
+        .unwrap();
+        let grid = sudoku.solutions().next().unwrap();
+        assert!(is_valid(&sudoku, &grid));
+        assert_eq!(
+            sudoku.render(&grid),
+            "534678912\n672195348\n198342567\n859761423\n426853791\n\
+             713924856\n961537284\n287419635\n345286179\n"
+        );
+        assert!(sudoku.has_unique_solution());
+        assert!(Sudoku::parse(3, "123").is_none());
+    }
+
+    #[test]
+    fn test_variants() {
+        let mut sudoku = Sudoku::new(2, &[0; 16]);
+        assert!(!sudoku.has_unique_solution());
+        assert_eq!(count(&sudoku), 288);
+
+        sudoku.set_diagonals(true);
+        assert_eq!(count(&sudoku), 48);
+
+        let mut jigsaw = Sudoku::new(2, &[0; 16]);
+        jigsaw.set_regions(&[0, 0, 1, 1, 0, 0, 2, 1, 3, 2, 2, 1, 3, 3, 2, 3]);
+        assert_eq!(count(&jigsaw), 72);
+    }
+
+    #[test]
+    fn test_killer() {
+        assert_eq!(digit_sets(9, 3, 7), [0b1011]);
+        assert_eq!(digit_sets(9, 2, 10).len(), 4);
+
+        let mut killer = Sudoku::new(2, &[0; 16]);
+        killer.add_cage(&[0, 1], 3);
+        killer.add_cage(&[2, 3], 7);
+        killer.add_cage(&[4, 8], 5);
+        assert_eq!(count(&killer), 12);
+
+        // The two cells share no row, column or box, so only the cage keeps
+        // them from both holding 2.
+        let mut killer = Sudoku::new(2, &[0; 16]);
+        killer.add_cage(&[5, 10], 4);
+        assert_eq!(count(&killer), 36);
+    }
+}
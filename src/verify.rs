@@ -0,0 +1,145 @@
+#![allow(clippy::unnecessary_cast)]
+use std::error::Error;
+use std::fmt;
+
+use crate::spec::Spec;
+use crate::{Count, Data, OptSpec};
+
+// Why a set of options is not a solution. Items and options are numbered
+// as in the spec, from 0.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum VerifyError {
+    NoSuchOption(usize),
+    Repeated(usize),
+    // Options without primary items are never part of a solution.
+    NoPrimaryItem(usize),
+    Covered {
+        item: Count,
+        times: Data,
+        bounds: (Data, Data),
+    },
+    // Two options that share a secondary item without giving it the same
+    // color; an uncolored secondary item may be in one option at most.
+    Clash {
+        item: Count,
+        options: (usize, usize),
+    },
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            VerifyError::NoSuchOption(k) => write!(f, "no option {k}"),
+            VerifyError::Repeated(k) => write!(f, "option {k} is repeated"),
+            VerifyError::NoPrimaryItem(k) => {
+                write!(f, "option {k} has no primary item")
+            }
+            VerifyError::Covered { item, times, bounds: (u, v) } => {
+                write!(f, "item {item} is covered {times} times")?;
+                match u == v {
+                    true => write!(f, ", not {u}"),
+                    false => write!(f, ", not {u} to {v}"),
+                }
+            }
+            VerifyError::Clash { item, options: (j, k) } => {
+                write!(f, "options {j} and {k} clash on item {item}")
+            }
+        }
+    }
+}
+
+impl Error for VerifyError {}
+
+// Checks that the chosen options, given by index, solve the problem: each
+// primary item is covered within its bounds, exactly once by default, and
+// each secondary item is in at most one option or gets the same color from
+// all of them. Uses nothing but the spec, so it can check solutions from
+// anywhere.
+pub fn verify_solution<S: OptSpec + Copy>(
+    spec: &Spec<S>,
+    chosen: &[usize],
+) -> Result<(), VerifyError> {
+    let np = spec.primary();
+    let n = np + spec.secondary();
+    let mut times = vec![0; np as usize];
+    // The first option with each secondary item, and the color it gives.
+    let mut first: Vec<Option<(usize, Data)>> = vec![None; n as usize];
+    for (a, &k) in chosen.iter().enumerate() {
+        let Some(opt) = spec.options().get(k) else {
+            return Err(VerifyError::NoSuchOption(k));
+        };
+        if chosen[..a].contains(&k) {
+            return Err(VerifyError::Repeated(k));
+        }
+        if opt.iter().all(|s| s.get_item() >= np) {
+            return Err(VerifyError::NoPrimaryItem(k));
+        }
+        for s in opt {
+            let (i, c) = (s.get_item(), s.get_color());
+            if i < np {
+                times[i as usize] += 1;
+                continue;
+            }
+            match first[i as usize] {
+                None => first[i as usize] = Some((k, c)),
+                Some((_, d)) if c != 0 && c == d => {}
+                Some((j, _)) => {
+                    let options = (j, k);
+                    return Err(VerifyError::Clash { item: i, options });
+                }
+            }
+        }
+    }
+    for i in 0..np {
+        let bounds = spec.bounds(i);
+        let times = times[i as usize];
+        if !(bounds.0..=bounds.1).contains(&times) {
+            return Err(VerifyError::Covered { item: i, times, bounds });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::queens;
+    use crate::spec::taocp_xcc;
+
+    #[test]
+    fn test_verify() {
+        let mut spec = taocp_xcc();
+        spec.add_option([(3, 1), (4, 2)]);
+        assert_eq!(verify_solution(&spec, &[3, 1]), Ok(()));
+        for (chosen, err) in [
+            (&[1, 6][..], VerifyError::NoSuchOption(6)),
+            (&[1, 3, 1], VerifyError::Repeated(1)),
+            (&[1, 3, 5], VerifyError::NoPrimaryItem(5)),
+            (&[2, 3], VerifyError::Clash { item: 3, options: (2, 3) }),
+            (&[0, 4], VerifyError::Clash { item: 4, options: (0, 4) }),
+            (&[1, 4], VerifyError::Clash { item: 4, options: (1, 4) }),
+            (
+                &[1],
+                VerifyError::Covered { item: 1, times: 0, bounds: (1, 1) },
+            ),
+        ] {
+            assert_eq!(verify_solution(&spec, chosen), Err(err));
+        }
+
+        // Without colors, secondary items may be in one option at most.
+        let mut spec = queens::spec(4);
+        assert_eq!(verify_solution(&spec, &[1, 7, 8, 14]), Ok(()));
+        let err = verify_solution(&spec, &[0, 5]).unwrap_err();
+        assert!(matches!(err, VerifyError::Clash { options: (0, 5), .. }));
+        for i in 0..spec.primary() {
+            spec.set_bounds(i, 0, 1);
+        }
+        assert_eq!(verify_solution(&spec, &[0]), Ok(()));
+        assert_eq!(verify_solution(&spec, &[]), Ok(()));
+
+        let err = VerifyError::Covered { item: 0, times: 3, bounds: (0, 2) };
+        assert_eq!(err.to_string(), "item 0 is covered 3 times, not 0 to 2");
+        let err = VerifyError::Clash { item: 4, options: (2, 0) };
+        assert_eq!(err.to_string(), "options 2 and 0 clash on item 4");
+    }
+}